@@ -6,7 +6,8 @@ use threerender::math::trs::{Rotation, Scale, Translation};
 use threerender::math::{Quat, Transform, Vec3};
 use threerender::mesh::{Plane, Sphere, Square};
 use threerender::renderer::Renderer;
-use threerender::traits::entity::{EntityDescriptor, ReflectionStyle};
+use threerender::traits::entity::EntityDescriptor;
+use threerender::traits::material::{Material, ReflectionStyle};
 use threerender::{
     CameraStyle, HemisphereLightStyle, LightBaseStyle, LightStyle, RendererBuilder, ShadowOptions,
     ShadowStyle, ShadowType,
@@ -154,14 +155,16 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "plane".to_owned(),
         mesh: Some(plane),
-        fill_color: RGBA::new(163, 104, 64, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(163, 104, 64, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., -2., -3.),
             Quat::from_axis_angle(0., -1., 0., 1.),
             Vec3::new(30., 30., 30.),
         ),
         state: Default::default(),
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
@@ -169,17 +172,20 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "sphere".to_owned(),
         mesh: Some(sphere),
-        fill_color: RGBA::new(255, 25, 255, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 25, 255, 255),
+            reflection: ReflectionStyle {
+                specular: 10.,
+                intensity: 100.,
+            },
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::ZERO,
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: ReflectionStyle {
-            specular: 10.,
-            intensity: 100.,
-        },
         children: vec![],
         ..Default::default()
     });
@@ -187,34 +193,40 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "square1".to_owned(),
         mesh: Some(square.clone()),
-        fill_color: RGBA::new(0, 255, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(0, 255, 0, 255),
+            reflection: ReflectionStyle {
+                specular: 10.,
+                intensity: 100.,
+            },
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(0., 0., -3.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: ReflectionStyle {
-            specular: 10.,
-            intensity: 100.,
-        },
         children: vec![],
         ..Default::default()
     });
     renderer_builder.push(EntityDescriptor {
         id: "square2".to_owned(),
         mesh: Some(square),
-        fill_color: RGBA::new(255, 0, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 0, 0, 255),
+            reflection: ReflectionStyle {
+                intensity: 0.,
+                specular: 0.,
+            },
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., 0., -1.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: ReflectionStyle {
-            intensity: 0.,
-            specular: 0.,
-        },
         children: vec![],
         ..Default::default()
     });
@@ -8,6 +8,7 @@ use threerender::mesh::{Line, Point, Topology};
 
 use threerender::renderer::Renderer;
 use threerender::traits::entity::{EntityDescriptor, EntityRendererState, RendererState};
+use threerender::traits::material::Material;
 use threerender::{CameraPosition, CameraStyle, RendererBuilder};
 
 struct App {}
@@ -74,7 +75,10 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "lines".to_owned(),
         mesh: Some(lines.clone()),
-        fill_color: RGBA::new(255, 0, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 0, 0, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(0., 0., 0.),
             Quat::default(),
@@ -84,7 +88,6 @@ fn build() -> RendererBuilder {
             topology: lines.topology,
             ..Default::default()
         },
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
@@ -102,7 +105,10 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "circle".to_owned(),
         mesh: Some(points.clone()),
-        fill_color: RGBA::new(0, 0, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(0, 0, 0, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(0., 0., 0.),
             Quat::default(),
@@ -112,7 +118,6 @@ fn build() -> RendererBuilder {
             topology: points.topology,
             ..Default::default()
         },
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
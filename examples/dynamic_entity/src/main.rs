@@ -9,6 +9,7 @@ use threerender::mesh::Mesh;
 use threerender::mesh::Sphere;
 use threerender::renderer::Renderer;
 use threerender::traits::entity::EntityDescriptor;
+use threerender::traits::material::Material;
 use threerender::{CameraStyle, LightBaseStyle, LightStyle, RendererBuilder};
 
 trait Random {
@@ -40,14 +41,16 @@ impl Updater for App {
             renderer.push_entity(EntityDescriptor {
                 id: format!("sphere{}", renderer.entities().len()),
                 mesh: Some(self.sphere.clone()),
-                fill_color: RGBA::new(r, g, b, 255),
+                material: Rc::new(Material {
+                    base_color: RGBA::new(r, g, b, 255),
+                    ..Default::default()
+                }),
                 transform: Transform::from_translation_rotation_scale(
                     Vec3::new(x, y, z),
                     Quat::default(),
                     Vec3::ONE,
                 ),
                 state: Default::default(),
-                reflection: Default::default(),
                 children: vec![],
                 ..Default::default()
             })
@@ -80,14 +83,16 @@ fn build() -> (RendererBuilder, Rc<Sphere>) {
     renderer_builder.push(EntityDescriptor {
         id: "sphere".to_owned(),
         mesh: Some(sphere.clone()),
-        fill_color: RGBA::new(255, 255, 255, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 255, 255, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::ZERO,
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
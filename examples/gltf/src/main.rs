@@ -7,7 +7,8 @@ use threerender::math::trs::{Rotation, Scale, Translation};
 use threerender::math::{Quat, Transform, Vec3};
 use threerender::mesh::Plane;
 use threerender::renderer::Renderer;
-use threerender::traits::entity::{EntityDescriptor, ReflectionStyle};
+use threerender::traits::entity::EntityDescriptor;
+use threerender::traits::material::Material;
 use threerender::{
     CameraPosition, CameraStyle, HemisphereLightStyle, LightBaseStyle, LightStyle, RendererBuilder,
     ShadowOptions, ShadowStyle, ShadowType,
@@ -98,7 +99,7 @@ impl GltfHandler for OwnGltfHandler {
     {
         #[cfg(feature = "avocado")]
         {
-            _descriptor.receive_shadow = false;
+            _descriptor.material_mut().receive_shadow = false;
         }
     }
 }
@@ -171,14 +172,16 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "plane".to_owned(),
         mesh: Some(plane),
-        fill_color: RGBA::new(163, 104, 64, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(163, 104, 64, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., -2., 0.),
             Quat::from_axis_angle(0., -1., 0., 1.),
             Vec3::new(30., 30., 30.),
         ),
         state: Default::default(),
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
@@ -211,7 +214,10 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "model".to_string(),
         mesh: None,
-        fill_color: RGBA::default(),
+        material: Rc::new(Material {
+            base_color: RGBA::default(),
+            ..Default::default()
+        }),
         transform: Transform {
             #[cfg(feature = "avocado")]
             translation: Vec3::new(0., 1., 0.),
@@ -228,7 +234,6 @@ fn build() -> RendererBuilder {
             ..Default::default()
         },
         state: Default::default(),
-        reflection: ReflectionStyle::default(),
         children: gltf_loader.entities,
         ..Default::default()
     });
@@ -7,6 +7,7 @@ use threerender::math::{Quat, Transform, Vec3};
 use threerender::mesh::{Sphere, Square};
 use threerender::renderer::Renderer;
 use threerender::traits::entity::EntityDescriptor;
+use threerender::traits::material::Material;
 use threerender::{CameraStyle, LightBaseStyle, LightStyle, RendererBuilder};
 
 #[derive(Default)]
@@ -110,14 +111,16 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "sphere".to_owned(),
         mesh: Some(sphere),
-        fill_color: RGBA::new(255, 255, 255, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 255, 255, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::ZERO,
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
@@ -125,28 +128,32 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "square1".to_owned(),
         mesh: Some(square.clone()),
-        fill_color: RGBA::new(0, 255, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(0, 255, 0, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(0., 0., -3.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
     renderer_builder.push(EntityDescriptor {
         id: "square2".to_owned(),
         mesh: Some(square),
-        fill_color: RGBA::new(255, 0, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 0, 0, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., 0., -1.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         children: vec![],
         ..Default::default()
     });
@@ -7,6 +7,7 @@ use threerender::math::{Quat, Transform, Vec3};
 use threerender::mesh::{Plane, Sphere, Square};
 use threerender::renderer::Renderer;
 use threerender::traits::entity::EntityDescriptor;
+use threerender::traits::material::Material;
 use threerender::{
     CameraStyle, LightBaseStyle, LightStyle, RendererBuilder, ShadowOptions, ShadowStyle,
     ShadowType,
@@ -115,55 +116,63 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "plane".to_owned(),
         mesh: Some(plane),
-        fill_color: RGBA::new(255, 255, 255, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 255, 255, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., -5., -3.),
             Quat::default(),
             Vec3::new(10., 10., 10.),
         ),
         state: Default::default(),
-        reflection: Default::default(),
         ..Default::default()
     });
     let sphere = Rc::new(Sphere::new(50, 50, None));
     renderer_builder.push(EntityDescriptor {
         id: "sphere".to_owned(),
         mesh: Some(sphere),
-        fill_color: RGBA::new(255, 255, 255, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 255, 255, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::ZERO,
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         ..Default::default()
     });
     let square = Rc::new(Square::new(None));
     renderer_builder.push(EntityDescriptor {
         id: "square1".to_owned(),
         mesh: Some(square.clone()),
-        fill_color: RGBA::new(0, 255, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(0, 255, 0, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(0., 0., -3.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         ..Default::default()
     });
     renderer_builder.push(EntityDescriptor {
         id: "square2".to_owned(),
         mesh: Some(square),
-        fill_color: RGBA::new(255, 0, 0, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 0, 0, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., 0., -1.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: Default::default(),
-        reflection: Default::default(),
         ..Default::default()
     });
     renderer_builder
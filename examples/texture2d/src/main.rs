@@ -8,6 +8,7 @@ use threerender::mesh::{BuiltInEntityOption, Plane, Sphere, Square};
 use threerender::renderer::Renderer;
 use threerender::traits::entity::{EntityDescriptor, EntityRendererState};
 use threerender::traits::image::DefaultImage;
+use threerender::traits::material::{Material, TextureSlot};
 use threerender::{CameraStyle, LightBaseStyle, LightStyle, RendererBuilder, ShadowStyle};
 
 struct App;
@@ -57,14 +58,16 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "plane".to_owned(),
         mesh: Some(plane),
-        fill_color: RGBA::new(255, 255, 255, 255),
+        material: Rc::new(Material {
+            base_color: RGBA::new(255, 255, 255, 255),
+            ..Default::default()
+        }),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-3., -5., -3.),
             Quat::default(),
             Vec3::new(10., 10., 10.),
         ),
         state: Default::default(),
-        reflection: Default::default(),
         ..Default::default()
     });
 
@@ -74,17 +77,22 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "square".to_owned(),
         mesh: Some(square),
-        fill_color: RGBA::new(255, 255, 0, 255),
+        material: Rc::new(
+            Material {
+                base_color: RGBA::new(255, 255, 0, 255),
+                ..Default::default()
+            }
+            .with_texture(
+                TextureSlot::BaseColor,
+                Rc::new(DefaultImage::from_buffer(im).expect("Image load error")),
+            ),
+        ),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-1., 0., -2.),
             Quat::default(),
             Vec3::ONE,
         ),
         state: EntityRendererState::default(),
-        reflection: Default::default(),
-        texture: Some(Rc::new(
-            DefaultImage::from_buffer(im).expect("Image load error"),
-        )),
         ..Default::default()
     });
 
@@ -95,17 +103,22 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "plane".to_owned(),
         mesh: Some(plane),
-        fill_color: RGBA::new(0, 255, 255, 255),
+        material: Rc::new(
+            Material {
+                base_color: RGBA::new(0, 255, 255, 255),
+                ..Default::default()
+            }
+            .with_texture(
+                TextureSlot::BaseColor,
+                Rc::new(DefaultImage::from_buffer(im).expect("Image load error")),
+            ),
+        ),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(-1., 0., 1.),
             Quat::from_axis_angle(0., 0.5, 0., 1.),
             Vec3::ONE,
         ),
         state: EntityRendererState::default(),
-        reflection: Default::default(),
-        texture: Some(Rc::new(
-            DefaultImage::from_buffer(im).expect("Image load error"),
-        )),
         ..Default::default()
     });
 
@@ -117,17 +130,25 @@ fn build() -> RendererBuilder {
     renderer_builder.push(EntityDescriptor {
         id: "sphere".to_owned(),
         mesh: Some(sphere),
-        fill_color: RGBA::new(255, 255, 255, 255),
+        material: Rc::new(
+            Material {
+                base_color: RGBA::new(255, 255, 255, 255),
+                ..Default::default()
+            }
+            .with_texture(
+                TextureSlot::BaseColor,
+                Rc::new(
+                    DefaultImage::from_buffer(include_bytes!("../globe.jpg"))
+                        .expect("Image load error"),
+                ),
+            ),
+        ),
         transform: Transform::from_translation_rotation_scale(
             Vec3::new(2., 0., 1.),
             Quat::from_axis_angle(0., 0.5, 0., 1.),
             Vec3::ONE,
         ),
         state: EntityRendererState::default(),
-        reflection: Default::default(),
-        texture: Some(Rc::new(
-            DefaultImage::from_buffer(include_bytes!("../globe.jpg")).expect("Image load error"),
-        )),
         ..Default::default()
     });
     renderer_builder
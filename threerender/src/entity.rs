@@ -1,22 +1,99 @@
-use threerender_color::rgb::RGBA;
+use std::{collections::HashMap, rc::Rc};
+
+use getset::Getters;
 use threerender_math::{
     trs::{Rotation, Scale, Translation},
     Quat, Transform, Vec3,
 };
-use threerender_traits::entity::{EntityRendererState, ReflectionStyle};
+use threerender_traits::{
+    entity::{EntityInstance, EntityRendererState},
+    material::{Material, TextureSlot},
+};
 
 /// An entity to render actually.
-#[derive(Debug, Default, Clone)]
+/// The uniforms of the entity are uploaded only when the entity is changed through the mutators.
+#[derive(Debug, Default, Clone, Getters)]
 pub struct Entity {
     pub id: String,
-    pub fill_color: RGBA,
-    pub transform: Transform,
-    pub reflection: ReflectionStyle,
+    #[getset(get = "pub")]
+    pub(crate) transform: Transform,
+    #[getset(get = "pub")]
+    pub(crate) material: Rc<Material>,
     pub children: Vec<Entity>,
-    pub state: EntityRendererState,
-    pub normal_map_idx: Option<i32>,
-    pub tex_idx: Option<i32>,
-    pub receive_shadow: bool,
+    #[getset(get = "pub")]
+    pub(crate) state: EntityRendererState,
+    #[getset(get = "pub")]
+    pub(crate) instances: Vec<EntityInstance>,
+    // Index of the texture array for each texture slot of the material.
+    pub(crate) texture_indices: HashMap<TextureSlot, i32>,
+    // The entity and its children need to be uploaded.
+    pub(crate) dirty: bool,
+    // The textures of the material need to be resolved again.
+    pub(crate) textures_dirty: bool,
+    // The transform in the world space, which is updated when the entity is uploaded.
+    pub(crate) world_transform: Transform,
+}
+
+impl Entity {
+    /// Get the mutable material.
+    /// If the material is shared with other entities, the material is cloned for this entity.
+    /// The added textures are uploaded in the next rendering.
+    pub fn material_mut(&mut self) -> &mut Material {
+        self.dirty = true;
+        self.textures_dirty = true;
+        Rc::make_mut(&mut self.material)
+    }
+
+    /// Replace the material.
+    /// The added textures are uploaded in the next rendering.
+    pub fn set_material(&mut self, material: Rc<Material>) {
+        self.dirty = true;
+        self.textures_dirty = true;
+        self.material = material;
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        self.dirty = true;
+        &mut self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.dirty = true;
+        self.transform = transform;
+    }
+
+    pub fn instances_mut(&mut self) -> &mut Vec<EntityInstance> {
+        self.dirty = true;
+        &mut self.instances
+    }
+
+    pub fn set_instances(&mut self, instances: Vec<EntityInstance>) {
+        self.dirty = true;
+        self.instances = instances;
+    }
+
+    /// Get the mutable renderer state.
+    /// The state is validated against the prepared pipelines in the next rendering.
+    pub fn state_mut(&mut self) -> &mut EntityRendererState {
+        self.dirty = true;
+        &mut self.state
+    }
+
+    /// Replace the renderer state.
+    /// The state is validated against the prepared pipelines in the next rendering.
+    pub fn set_state(&mut self, state: EntityRendererState) {
+        self.dirty = true;
+        self.state = state;
+    }
+
+    /// Mark the entity as changed to upload it in the next rendering.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub(crate) fn texture_idx(&self, slot: TextureSlot) -> Option<i32> {
+        self.texture_indices.get(&slot).copied()
+    }
 }
 
 impl Translation for Entity {
@@ -24,6 +101,7 @@ impl Translation for Entity {
         &self.transform.translation
     }
     fn translation_mut(&mut self) -> &mut Vec3 {
+        self.dirty = true;
         &mut self.transform.translation
     }
 }
@@ -33,6 +111,7 @@ impl Rotation for Entity {
         &self.transform.rotation
     }
     fn rotation_mut(&mut self) -> &mut Quat {
+        self.dirty = true;
         &mut self.transform.rotation
     }
 }
@@ -42,6 +121,7 @@ impl Scale for Entity {
         &self.transform.scale
     }
     fn scale_mut(&mut self) -> &mut Vec3 {
+        self.dirty = true;
         &mut self.transform.scale
     }
 }
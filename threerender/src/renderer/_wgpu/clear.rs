@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+
+use wgpu::{Device, RenderPass, RenderPipeline, TextureFormat};
+
+use crate::ClearPolicy;
+
+use super::unit::rgba_to_array_64;
+
+// Pipelines to clear the part of the render target which is limited by the scissor rect.
+pub(super) struct ClearPipelines {
+    color: RenderPipeline,
+    depth: RenderPipeline,
+}
+
+impl ClearPipelines {
+    pub(super) fn new(
+        device: &Device,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("clear"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/clear.wgsl"))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("clear"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |write_mask| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("clear"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        // Replace the color with the blend constant.
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::Constant,
+                                dst_factor: wgpu::BlendFactor::Zero,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::Constant,
+                                dst_factor: wgpu::BlendFactor::Zero,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                        }),
+                        write_mask,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: depth_format,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        };
+
+        Self {
+            color: create_pipeline(wgpu::ColorWrites::ALL),
+            depth: create_pipeline(wgpu::ColorWrites::empty()),
+        }
+    }
+
+    pub(super) fn clear<'a>(&'a self, rpass: &mut RenderPass<'a>, policy: &ClearPolicy) {
+        let (pipeline, [r, g, b, a]) = match policy {
+            ClearPolicy::None => return,
+            ClearPolicy::Depth => (&self.depth, [0.; 4]),
+            ClearPolicy::Color(color) => (&self.color, rgba_to_array_64(color)),
+        };
+        rpass.set_pipeline(pipeline);
+        rpass.set_blend_constant(wgpu::Color { r, g, b, a });
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,249 @@
+use glam::{Mat4, Vec3, Vec4};
+use threerender_math::Transform;
+
+use crate::mesh::Vertex;
+
+// Bounding sphere in the local space of the mesh.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BoundingSphere {
+    center: Vec3,
+    radius: f32,
+}
+
+impl BoundingSphere {
+    pub(super) fn from_vertices(vertices: &[Vertex]) -> Option<Self> {
+        let mut positions = vertices
+            .iter()
+            .map(|v| Vec3::new(v.pos[0], v.pos[1], v.pos[2]));
+        let first = positions.next()?;
+        let (min, max) = positions.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        let center = (min + max) * 0.5;
+        Some(Self {
+            center,
+            radius: (max - center).length(),
+        })
+    }
+
+    // The radius is scaled by the largest axis to keep the sphere conservative.
+    pub(super) fn transform(&self, transform: &Transform) -> Self {
+        Self {
+            center: transform.as_mat4().transform_point3(self.center),
+            radius: self.radius * transform.scale.as_glam().abs().max_element(),
+        }
+    }
+
+    // Same as `transform`, but the scale is taken from the length of the axes.
+    pub(super) fn transform_mat4(&self, m: &Mat4) -> Self {
+        let scale = m
+            .x_axis
+            .truncate()
+            .length()
+            .max(m.y_axis.truncate().length())
+            .max(m.z_axis.truncate().length());
+        Self {
+            center: m.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+
+    // The sphere which encloses all spheres.
+    pub(super) fn from_spheres(spheres: impl IntoIterator<Item = Self>) -> Option<Self> {
+        let spheres: Vec<_> = spheres.into_iter().collect();
+        let (min, max) = spheres.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), s| (min.min(s.center - s.radius), max.max(s.center + s.radius)),
+        );
+        let center = (min + max) * 0.5;
+        let radius = spheres
+            .iter()
+            .map(|s| (s.center - center).length() + s.radius)
+            .reduce(f32::max)?;
+        Some(Self { center, radius })
+    }
+}
+
+pub(super) struct Frustum {
+    // Each plane is `(normal, distance)` and the normal faces the inside.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // Extract the planes from the view projection matrix.
+    // The depth range of clip space is `0..1` in wgpu.
+    pub(super) fn from_mat4(m: &Mat4) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        Self {
+            planes: [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2]
+                .map(|p| p / p.truncate().length()),
+        }
+    }
+
+    // An invalid frustum (e.g. zero sized camera) doesn't cull anything.
+    pub(super) fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        !self
+            .planes
+            .iter()
+            .any(|p| p.truncate().dot(sphere.center) + p.w < -sphere.radius)
+    }
+}
+
+pub(super) struct Ray {
+    pub(super) origin: Vec3,
+    // Normalized
+    pub(super) direction: Vec3,
+}
+
+impl Ray {
+    // Unproject the point in normalized device coordinates from the near plane to the far plane.
+    pub(super) fn from_ndc(x: f32, y: f32, view_proj: &Mat4) -> Self {
+        let inv = view_proj.inverse();
+        let near = inv.project_point3(Vec3::new(x, y, 0.));
+        let far = inv.project_point3(Vec3::new(x, y, 1.));
+        Self {
+            origin: near,
+            direction: (far - near).normalize(),
+        }
+    }
+
+    pub(super) fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    pub(super) fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        let to_center = sphere.center - self.origin;
+        let radius_squared = sphere.radius * sphere.radius;
+        // The origin is inside the sphere.
+        if to_center.length_squared() <= radius_squared {
+            return true;
+        }
+        let t = to_center.dot(self.direction);
+        t >= 0. && (to_center - self.direction * t).length_squared() <= radius_squared
+    }
+
+    // Möller–Trumbore algorithm.
+    // Returns the distance from the origin to the intersection point.
+    pub(super) fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let det = edge1.dot(p);
+        // The ray is parallel to the triangle.
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let inv_det = 1. / det;
+
+        let s = self.origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        (t >= 0.).then_some(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts;
+
+    use super::*;
+    use crate::mesh::vertex;
+
+    fn sphere_at(x: f32, y: f32, z: f32) -> BoundingSphere {
+        BoundingSphere {
+            center: Vec3::new(x, y, z),
+            radius: 1.,
+        }
+    }
+
+    #[test]
+    fn test_frustum_intersects_sphere() {
+        let projection = Mat4::perspective_rh(consts::FRAC_PI_4, 1., 1., 100.);
+        let view = Mat4::look_at_rh(Vec3::new(0., 0., 10.), Vec3::ZERO, Vec3::Y);
+        let frustum = Frustum::from_mat4(&(projection * view));
+
+        assert!(frustum.intersects_sphere(&sphere_at(0., 0., 0.)));
+        // Partially inside
+        assert!(frustum.intersects_sphere(&sphere_at(0., 0., 9.5)));
+        // Behind the camera
+        assert!(!frustum.intersects_sphere(&sphere_at(0., 0., 20.)));
+        // Beyond the far plane
+        assert!(!frustum.intersects_sphere(&sphere_at(0., 0., -100.)));
+        // Out of the side
+        assert!(!frustum.intersects_sphere(&sphere_at(20., 0., 0.)));
+    }
+
+    #[test]
+    fn test_ray_intersect_triangle() {
+        let ray = Ray {
+            origin: Vec3::new(0., 0., 10.),
+            direction: Vec3::new(0., 0., -1.),
+        };
+        let (a, b, c) = (
+            Vec3::new(-1., -1., 0.),
+            Vec3::new(1., -1., 0.),
+            Vec3::new(0., 1., 0.),
+        );
+        assert_eq!(ray.intersect_triangle(a, b, c), Some(10.));
+        // Back face is also hit.
+        assert_eq!(ray.intersect_triangle(a, c, b), Some(10.));
+
+        let offset = Vec3::new(3., 0., 0.);
+        assert_eq!(
+            ray.intersect_triangle(a + offset, b + offset, c + offset),
+            None
+        );
+
+        let behind = Vec3::new(0., 0., 20.);
+        assert_eq!(
+            ray.intersect_triangle(a + behind, b + behind, c + behind),
+            None
+        );
+
+        assert!(ray.intersects_sphere(&sphere_at(0., 0., 0.)));
+        assert!(!ray.intersects_sphere(&sphere_at(3., 0., 0.)));
+    }
+
+    #[test]
+    fn test_bounding_sphere() {
+        let vertices = [
+            vertex([-1., 0., 0., 1.], [0., 0., 0.]),
+            vertex([3., 2., 0., 1.], [0., 0., 0.]),
+        ];
+        let sphere = BoundingSphere::from_vertices(&vertices).unwrap();
+        assert_eq!(sphere.center, Vec3::new(1., 1., 0.));
+
+        let transform = Transform::from_translation_rotation_scale(
+            threerender_math::Vec3::new(1., 0., 0.),
+            Default::default(),
+            threerender_math::Vec3::new(1., 2., 1.),
+        );
+        let sphere = sphere.transform(&transform);
+        assert_eq!(sphere.center, Vec3::new(2., 2., 0.));
+        assert_eq!(sphere.radius, 5_f32.sqrt() * 2.);
+
+        // The instances are enclosed by the merged sphere.
+        let instances = [
+            Mat4::from_translation(Vec3::new(-10., 0., 0.)),
+            Mat4::from_scale_rotation_translation(
+                Vec3::splat(2.),
+                Default::default(),
+                Vec3::new(10., 0., 0.),
+            ),
+        ];
+        let unit = sphere_at(0., 0., 0.);
+        let merged =
+            BoundingSphere::from_spheres(instances.iter().map(|m| unit.transform_mat4(m))).unwrap();
+        assert_eq!(merged.center, Vec3::new(0.5, 0., 0.));
+        assert_eq!(merged.radius, 11.5);
+        assert!(BoundingSphere::from_spheres([]).is_none());
+    }
+}
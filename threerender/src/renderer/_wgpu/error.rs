@@ -0,0 +1,21 @@
+use threerender_traits::entity::EntityRendererState;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RendererError {
+    #[error("Failed to create surface: {0}")]
+    CreateSurface(#[from] wgpu::CreateSurfaceError),
+    #[error("Failed to find an appropriate adapter")]
+    AdapterNotFound,
+    #[error("Failed to create device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error("Surface isn't supported by the adapter")]
+    UnsupportedSurface,
+    #[error("Failed to acquire the surface texture: {0}")]
+    Surface(#[from] wgpu::SurfaceError),
+    #[error("You already have a window as render target view")]
+    SurfaceTarget,
+    #[error("Specified renderer state is not found: {0:?}")]
+    StateNotFound(EntityRendererState),
+    #[error("Failed to map the buffer: {0}")]
+    BufferAsync(#[from] wgpu::BufferAsyncError),
+}
@@ -0,0 +1,123 @@
+use super::texel::ChannelType;
+
+// A level of the mip chain. The rows are tightly packed.
+pub(super) struct MipLevel {
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) data: Vec<u8>,
+}
+
+// Count the levels until the size becomes 1x1.
+pub(super) fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    match v <= 0.04045 {
+        true => v / 12.92,
+        false => ((v + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    match v <= 0.0031308 {
+        true => v * 12.92,
+        false => 1.055 * v.powf(1. / 2.4) - 0.055,
+    }
+}
+
+// Downsample the image with the box filter for each level except the base level.
+// If `srgb` is true, the color channels are averaged in linear space and the alpha channel as is.
+pub(super) fn generate_mip_chain(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+    channel: ChannelType,
+    srgb: bool,
+) -> Vec<MipLevel> {
+    let bpp = bytes_per_pixel as usize;
+    let channel_size = channel.size();
+    let is_color = |c: usize| srgb && c / channel_size < 3;
+
+    let mut levels: Vec<MipLevel> = vec![];
+    let (mut src_width, mut src_height) = (width, height);
+    for _ in 1..mip_level_count(width, height) {
+        let src = levels.last().map_or(data, |l| &l.data);
+        let (dst_width, dst_height) = ((src_width / 2).max(1), (src_height / 2).max(1));
+        let mut dst = vec![0; dst_width as usize * dst_height as usize * bpp];
+        for y in 0..dst_height {
+            for x in 0..dst_width {
+                let xs = [x * 2, (x * 2 + 1).min(src_width - 1)];
+                let ys = [y * 2, (y * 2 + 1).min(src_height - 1)];
+                let dst_offset = (y * dst_width + x) as usize * bpp;
+                for c in (0..bpp).step_by(channel_size) {
+                    let sum: f32 = ys
+                        .iter()
+                        .flat_map(|sy| xs.iter().map(move |sx| (sy * src_width + sx) as usize))
+                        .map(|i| channel.read(&src[i * bpp + c..]))
+                        .map(|v| match is_color(c) {
+                            true => srgb_to_linear(v),
+                            false => v,
+                        })
+                        .sum();
+                    let avg = match is_color(c) {
+                        true => linear_to_srgb(sum / 4.),
+                        false => sum / 4.,
+                    };
+                    channel.write(avg, &mut dst[dst_offset + c..dst_offset + c + channel_size]);
+                }
+            }
+        }
+        levels.push(MipLevel {
+            width: dst_width,
+            height: dst_height,
+            data: dst,
+        });
+        (src_width, src_height) = (dst_width, dst_height);
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mip_level_count() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(256, 256), 9);
+        assert_eq!(mip_level_count(300, 20), 9);
+    }
+
+    #[test]
+    fn test_generate_mip_chain() {
+        #[rustfmt::skip]
+        let data = [
+            0, 0, 0, 255,     100, 0, 0, 255,     0, 0, 0, 255,
+            0, 100, 0, 255,   0, 0, 100, 255,     0, 0, 0, 255,
+        ];
+        let levels = generate_mip_chain(&data, 3, 2, 4, ChannelType::Unorm8, false);
+        assert_eq!(levels.len(), 1);
+        assert_eq!((levels[0].width, levels[0].height), (1, 1));
+        assert_eq!(levels[0].data, vec![25, 25, 25, 255]);
+
+        let data = [0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff];
+        let levels = generate_mip_chain(&data, 2, 1, 4, ChannelType::Unorm16, false);
+        assert_eq!(levels[0].data, vec![0x00, 0x80, 0x00, 0x80]);
+
+        let data = [1_f32, 0., 0., 0., 0., 0., 0., 0.]
+            .map(f32::to_le_bytes)
+            .concat();
+        let levels = generate_mip_chain(&data, 2, 1, 16, ChannelType::Float32, false);
+        assert_eq!(ChannelType::Float32.read(&levels[0].data), 0.5);
+    }
+
+    #[test]
+    fn test_generate_mip_chain_srgb() {
+        let data = [255, 255, 255, 255, 0, 0, 0, 0];
+        let levels = generate_mip_chain(&data, 2, 1, 4, ChannelType::Unorm8, true);
+        // The linear average 0.5 is encoded as 188 in sRGB, while the alpha is kept linear.
+        assert_eq!(levels[0].data, vec![188, 188, 188, 128]);
+    }
+}
@@ -1,10 +1,19 @@
 pub mod builder;
+mod clear;
+mod culling;
+mod error;
+mod mipmap;
+mod post_process;
 mod processor;
+mod render_target;
 mod renderer;
 mod scene;
 mod shadow;
+mod skybox;
+mod texel;
 mod uniform;
 mod unit;
 
 pub use builder as wgpu_builder;
+pub use error::RendererError;
 pub use renderer::*;
@@ -0,0 +1,294 @@
+use std::{borrow::Cow, mem};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, Queue,
+    RenderPipeline, Sampler, TextureFormat, TextureView,
+};
+
+use crate::{PostEffect, PostProcessStyle};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PostProcessData {
+    texel_size: [f32; 2],
+    exposure: f32,
+    tone_mapping: u32,
+}
+
+impl PostProcessData {
+    fn new(style: &PostProcessStyle, width: u32, height: u32) -> Self {
+        Self {
+            texel_size: [1. / width.max(1) as f32, 1. / height.max(1) as f32],
+            exposure: *style.exposure(),
+            tone_mapping: style.tone_mapping().as_u32(),
+        }
+    }
+}
+
+// Two HDR targets which are read and written alternately by the passes.
+struct PingPong {
+    views: [TextureView; 2],
+    // `bind_groups[i]` reads `views[i]`.
+    bind_groups: [BindGroup; 2],
+}
+
+impl PingPong {
+    fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        uniform: &Buffer,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let views = [0, 1].map(|_| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("HDR Target"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: PostProcessor::HDR_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Process"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+        Self { views, bind_groups }
+    }
+}
+
+// Render the scene into the HDR target, and apply the effects and the tone mapping.
+pub(super) struct PostProcessor {
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    uniform: Buffer,
+    data: PostProcessData,
+    targets: PingPong,
+    effects: Vec<RenderPipeline>,
+    tone_mapping: RenderPipeline,
+}
+
+impl PostProcessor {
+    pub(super) const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    pub(super) fn new(
+        device: &Device,
+        style: &PostProcessStyle,
+        output_format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Process"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            mem::size_of::<PostProcessData>() as _
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Process"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let data = PostProcessData::new(style, width, height);
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Process Uniform Buffer"),
+            contents: bytemuck::bytes_of(&data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let targets = PingPong::new(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &uniform,
+            width,
+            height,
+        );
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Process"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label: &str, fragment: &str, format: TextureFormat| {
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!(
+                    "{}\n{}",
+                    include_str!("shaders/post_process/common.wgsl"),
+                    fragment
+                ))),
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let effects = style
+            .effects()
+            .iter()
+            .map(|effect| match effect {
+                PostEffect::Fxaa => create_pipeline(
+                    "FXAA",
+                    include_str!("shaders/post_process/fxaa.wgsl"),
+                    Self::HDR_FORMAT,
+                ),
+                PostEffect::Custom { label, source } => {
+                    create_pipeline(label, source, Self::HDR_FORMAT)
+                }
+            })
+            .collect();
+        let tone_mapping = create_pipeline(
+            "Tone Mapping",
+            include_str!("shaders/post_process/tone_mapping.wgsl"),
+            output_format,
+        );
+
+        Self {
+            bind_group_layout,
+            sampler,
+            uniform,
+            data,
+            targets,
+            effects,
+            tone_mapping,
+        }
+    }
+
+    // The scene is rendered into this view.
+    pub(super) fn input_view(&self) -> &TextureView {
+        &self.targets.views[0]
+    }
+
+    pub(super) fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.targets = PingPong::new(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.uniform,
+            width,
+            height,
+        );
+    }
+
+    // The uniform is uploaded only when the data is changed.
+    pub(super) fn update(
+        &mut self,
+        queue: &Queue,
+        style: &PostProcessStyle,
+        width: u32,
+        height: u32,
+    ) {
+        let data = PostProcessData::new(style, width, height);
+        if bytemuck::bytes_of(&data) == bytemuck::bytes_of(&self.data) {
+            return;
+        }
+        self.data = data;
+        queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&self.data));
+    }
+
+    pub(super) fn run(&self, encoder: &mut CommandEncoder, output: &TextureView) {
+        encoder.push_debug_group("post process pass");
+        let mut input = 0;
+        for pipeline in &self.effects {
+            self.draw(encoder, pipeline, input, &self.targets.views[1 - input]);
+            input = 1 - input;
+        }
+        self.draw(encoder, &self.tone_mapping, input, output);
+        encoder.pop_debug_group();
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        input: usize,
+        output: &TextureView,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, &self.targets.bind_groups[input], &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
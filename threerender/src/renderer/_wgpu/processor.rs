@@ -6,6 +6,7 @@ use shader_processor::{EnvType, ShaderProcessor};
 pub(super) struct ProcessOption {
     pub(super) has_texture: bool,
     pub(super) max_light_num: u32,
+    pub(super) cube_array: bool,
 }
 
 pub(super) struct Processor<'a>(ShaderProcessor<'a>);
@@ -33,6 +34,10 @@ impl<'a> Processor<'a> {
         s.insert_builtin("light::directional", p);
         let p = make_builtin_path("light/hemisphere");
         s.insert_builtin("light::hemisphere", p);
+        let p = make_builtin_path("light/point");
+        s.insert_builtin("light::point", p);
+        let p = make_builtin_path("light/spot");
+        s.insert_builtin("light::spot", p);
         let p = make_builtin_path("light/mod");
         s.insert_builtin("light", p);
 
@@ -40,6 +45,14 @@ impl<'a> Processor<'a> {
         let p = make_builtin_path("reflection");
         s.insert_builtin("reflection", p);
 
+        // pbr
+        let p = make_builtin_path("pbr");
+        s.insert_builtin("pbr", p);
+
+        // fog
+        let p = make_builtin_path("fog");
+        s.insert_builtin("fog", p);
+
         // shadow builtin modules
         let p = make_builtin_path("light/shadow/uniforms");
         s.insert_builtin("light::shadow::uniforms", p);
@@ -49,12 +62,19 @@ impl<'a> Processor<'a> {
         s.insert_builtin("light::shadow::normal", p);
         let p = make_builtin_path("light/shadow/pcss");
         s.insert_builtin("light::shadow::pcss", p);
+        let p = make_builtin_path("light/shadow/evsm");
+        s.insert_builtin("light::shadow::evsm", p);
+        let p = make_builtin_path("light/shadow/variance");
+        s.insert_builtin("light::shadow::variance", p);
+        let p = make_builtin_path("light/shadow/cube");
+        s.insert_builtin("light::shadow::cube", p);
         let p = make_builtin_path("light/shadow/mod");
         s.insert_builtin("light::shadow", p);
 
         // condition envs
         s.insert_env("HAS_TEXTURE", EnvType::Bool(option.has_texture));
         s.insert_env("MAX_LIGHT_NUM", EnvType::Number(option.max_light_num));
+        s.insert_env("CUBE_ARRAY", EnvType::Bool(option.cube_array));
 
         s.process().unwrap()
     }
@@ -0,0 +1,89 @@
+use wgpu::{Device, TextureFormat, TextureView};
+
+use crate::RenderTargetStyle;
+
+use super::{
+    scene::{Scene, SceneData, SceneUniform},
+    RenderedTexture,
+};
+
+// GPU resources to render the scene into the texture of `RenderTargetStyle`.
+pub(super) struct RenderedTarget {
+    // Index of the texture array which the rendered texture is bound to.
+    pub(super) texture_idx: i32,
+    pub(super) view: TextureView,
+    // Multi-sampled view which is resolved into `view`.
+    pub(super) msaa_view: Option<TextureView>,
+    pub(super) depth_view: TextureView,
+    pub(super) scene_uniform: SceneUniform,
+}
+
+impl RenderedTarget {
+    pub(super) fn new(
+        device: &Device,
+        style: &RenderTargetStyle,
+        scene: &Scene,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+        rendered_texture: &mut RenderedTexture,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: *style.width(),
+            height: *style.height(),
+            depth_or_array_layers: 1,
+        };
+        let sample_count = scene.config.max_samples.min(scene.scene.msaa_samples);
+        let create_texture = |label, sample_count, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+
+        let texture = create_texture(
+            "Render Target",
+            1,
+            format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let msaa_view = (sample_count > 1).then(|| {
+            create_texture(
+                "Multi-sampled Render Target",
+                sample_count,
+                format,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let depth_view = create_texture(
+            "Render Target Depth",
+            sample_count,
+            depth_format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let texture_idx = rendered_texture.push_render_target(
+            &style.texture(),
+            texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            device,
+        );
+        let scene_uniform =
+            SceneUniform::new(device, SceneData::from_camera(style.camera(), &scene.scene));
+
+        Self {
+            texture_idx,
+            view,
+            msaa_view,
+            depth_view,
+            scene_uniform,
+        }
+    }
+}
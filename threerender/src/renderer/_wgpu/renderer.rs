@@ -1,30 +1,39 @@
-use std::{borrow::Cow, collections::HashMap, io::Write, mem, num::NonZeroU32, rc::Rc};
+use std::{borrow::Cow, collections::HashMap, mem, num::NonZeroU32, rc::Rc, sync::mpsc};
 
-use glam::Mat3;
-use threerender_math::Transform;
+use glam::{Mat3, Mat4};
+use threerender_math::{Transform, Vec3};
 use threerender_traits::{
-    entity::{EntityDescriptor, EntityRendererState},
-    image::Image,
+    entity::{EntityDescriptor, EntityInstance, EntityRendererState},
+    image::{AddressMode, DefaultImage, FilterMode, Image, SamplerDescriptor},
+    material::TextureSlot,
 };
 use wgpu::{
     util::{align_to, DeviceExt},
-    vertex_attr_array, BindGroup, BindGroupLayout, Buffer, BufferAddress, Device, Features,
-    PrimitiveTopology, Queue, RenderPipeline, Sampler, ShaderModule, Surface, SurfaceConfiguration,
-    Texture, TextureView, VertexBufferLayout,
+    vertex_attr_array, BindGroup, BindGroupLayout, Buffer, BufferAddress, CommandEncoder, Device,
+    Features, IndexFormat, PrimitiveTopology, Queue, RenderPipeline, Sampler, ShaderModule,
+    Surface, SurfaceConfiguration, Texture, TextureView, VertexBufferLayout,
 };
 
 use crate::{
     entity::Entity,
-    mesh::{PolygonMode, TextureFormat, Topology, Vertex},
+    mesh::{BlendMode, CullMode, FrontFace, Mesh, PolygonMode, TextureFormat, Topology, Vertex},
     utils::vec::count_some,
-    RendererBuilder,
+    CameraStyle, ClearPolicy, RendererBuilder, Viewport,
 };
 
 use super::{
+    clear::ClearPipelines,
+    culling::{BoundingSphere, Frustum, Ray},
+    error::RendererError,
+    mipmap,
+    post_process::PostProcessor,
     processor::{ProcessOption, Processor},
-    scene::{Reflection, Scene},
-    shadow::ShadowBaker,
-    uniform::{EntityUniformBuffer, ShadowEntityUniformBuffer},
+    render_target::RenderedTarget,
+    scene::{is_float32_filterable, Pbr, Reflection, Scene},
+    shadow::{ShadowBaker, ShadowPipelineKey, ShadowTarget},
+    skybox::Skybox,
+    texel::{self, ChannelType},
+    uniform::{EntityUniformBuffer, InstanceBuffer, ShadowEntityUniformBuffer},
     unit::{rgba_to_array, rgba_to_array_64},
 };
 
@@ -35,6 +44,44 @@ struct RenderedEntityMeta {
     index_buf: Option<Buffer>,
     vertex_length: u32,
     index_length: u32,
+    index_format: IndexFormat,
+    // Keep the mesh to re-upload the vertices when the mesh is mutated.
+    mesh: Rc<dyn Mesh>,
+    use_tangent_space: bool,
+    // `None` means the entity is not instanced.
+    instance_buf: Option<Buffer>,
+    instance_length: u32,
+    // The uploaded instances to find the changed instances.
+    instances: Vec<InstanceBuffer>,
+    // `None` means the mesh has no vertices.
+    bounding: Option<BoundingSphere>,
+    // The bounding of all instances in the local space of the entity.
+    instance_bounding: Option<BoundingSphere>,
+    // The uniform needs to be uploaded regardless of the entity change,
+    // e.g. the entity is just created or the uniform buffer is recreated.
+    needs_upload: bool,
+}
+
+impl RenderedEntityMeta {
+    fn is_visible(&self, frustum: &Frustum, transform: &Transform) -> bool {
+        let bounding = match self.instance_buf {
+            Some(_) => &self.instance_bounding,
+            None => &self.bounding,
+        };
+        match bounding {
+            Some(bounding) => frustum.intersects_sphere(&bounding.transform(transform)),
+            None => false,
+        }
+    }
+
+    // This must be called when the mesh or the instances are changed.
+    fn update_instance_bounding(&mut self) {
+        self.instance_bounding = self.bounding.and_then(|bounding| {
+            BoundingSphere::from_spheres(self.instances.iter().map(|instance| {
+                bounding.transform_mat4(&Mat4::from_cols_array_2d(&instance.transform))
+            }))
+        });
+    }
 }
 
 // The struct will be depend on entity.
@@ -42,26 +89,50 @@ pub struct RenderedEntity {
     pub(super) entities: Vec<Entity>,
     meta_list: Vec<Option<RenderedEntityMeta>>,
     entity_uniform_buf: Buffer,
+    entity_uniform_alignment: BufferAddress,
     entity_bind_group: BindGroup,
     entity_bind_group_layout: BindGroupLayout,
+    // Single identity instance for the entity which is not instanced.
+    default_instance_buf: Buffer,
+    // Some entities may be changed, e.g. the entities are pushed or borrowed mutably.
+    // The entities are not traversed to upload them while this is false.
+    dirty: bool,
 }
 
 impl RenderedEntity {
+    // Return the indices as bytes with its format and length.
+    // 32-bit indices take precedence over 16-bit indices.
+    fn mesh_index(mesh: &dyn Mesh) -> Option<(&[u8], IndexFormat, u32)> {
+        match (mesh.index_u32(), mesh.index()) {
+            (Some(index), _) => Some((
+                bytemuck::cast_slice(index),
+                IndexFormat::Uint32,
+                index.len() as u32,
+            )),
+            (None, Some(index)) => Some((
+                bytemuck::cast_slice(index),
+                IndexFormat::Uint16,
+                index.len() as u32,
+            )),
+            (None, None) => None,
+        }
+    }
+
     fn make_entity(
         vertex: &[Vertex],
-        index: Option<&[u16]>,
+        index: Option<&[u8]>,
         device: &Device,
     ) -> (Buffer, Option<Buffer>, u32) {
         let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
             contents: bytemuck::cast_slice(vertex),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
         let index_buf = index.map(|index| {
             device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(index),
-                usage: wgpu::BufferUsages::INDEX,
+                contents: index,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             })
         });
 
@@ -70,6 +141,85 @@ impl RenderedEntity {
         (vertex_buf, index_buf, vertex_length)
     }
 
+    // Rewrite the vertex and index buffer with the current mesh data.
+    // The buffer is recreated only when the data doesn't fit into the existing buffer.
+    fn update_entity(meta: &mut RenderedEntityMeta, device: &Device, queue: &Queue) {
+        let vertex = match meta.use_tangent_space {
+            true => meta.mesh.as_tangent_space(),
+            false => meta.mesh.vertex(),
+        };
+        let vertex = vertex.borrow();
+        Self::write_or_grow_buffer(
+            &mut meta.vertex_buf,
+            bytemuck::cast_slice(vertex.as_slice()),
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            "Vertex Buffer",
+            device,
+            queue,
+        );
+        meta.vertex_length = vertex.len() as u32;
+        meta.bounding = BoundingSphere::from_vertices(vertex.as_slice());
+        meta.update_instance_bounding();
+
+        let index = Self::mesh_index(meta.mesh.as_ref());
+        match (index.map(|(index, _, _)| index), &mut meta.index_buf) {
+            (Some(index), Some(index_buf)) => Self::write_or_grow_buffer(
+                index_buf,
+                index,
+                wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                "Index Buffer",
+                device,
+                queue,
+            ),
+            (Some(index), None) => {
+                meta.index_buf = Some(device.create_buffer_init(
+                    &wgpu::util::BufferInitDescriptor {
+                        label: Some("Index Buffer"),
+                        contents: index,
+                        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                    },
+                ))
+            }
+            (None, _) => meta.index_buf = None,
+        }
+        if let Some((_, index_format, index_length)) = index {
+            meta.index_format = index_format;
+            meta.index_length = index_length;
+        } else {
+            meta.index_length = 0;
+        }
+    }
+
+    fn write_or_grow_buffer(
+        buf: &mut Buffer,
+        contents: &[u8],
+        usage: wgpu::BufferUsages,
+        label: &str,
+        device: &Device,
+        queue: &Queue,
+    ) {
+        // The size of `write_buffer` must be aligned.
+        let padded_len = align_to(contents.len(), wgpu::COPY_BUFFER_ALIGNMENT as usize);
+        let mut padded;
+        let contents = if padded_len != contents.len() {
+            padded = contents.to_vec();
+            padded.resize(padded_len, 0);
+            &padded[..]
+        } else {
+            contents
+        };
+
+        if contents.len() as BufferAddress <= buf.size() {
+            queue.write_buffer(buf, 0, contents);
+        } else {
+            *buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage,
+            });
+        }
+    }
+
     pub(super) fn make_uniform(
         device: &Device,
         length: usize,
@@ -130,6 +280,38 @@ impl RenderedEntity {
 
         (entity_bind_group_layout, entity_bind_group)
     }
+
+    fn make_instance_buffer(instances: &[InstanceBuffer], device: &Device) -> Option<Buffer> {
+        if instances.is_empty() {
+            return None;
+        }
+        Some(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents: bytemuck::cast_slice(instances),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            }),
+        )
+    }
+
+    fn make_default_instance_buffer(device: &Device) -> Buffer {
+        Self::make_instance_buffer(
+            &[InstanceBuffer::from_instance(&EntityInstance::default())],
+            device,
+        )
+        .expect("Default instance must exist")
+    }
+
+    // Reassign the uniform offsets to fill the slots of the removed entities.
+    fn compact_uniform_offsets(&mut self) {
+        compact_offsets(
+            self.meta_list
+                .iter_mut()
+                .flatten()
+                .map(|meta| (&mut meta.uniform_offset, &mut meta.needs_upload)),
+            self.entity_uniform_alignment,
+        );
+    }
 }
 
 // The struct will be depend on texture.
@@ -139,15 +321,50 @@ pub struct RenderedTexture {
     texture_bind_group: Option<BindGroup>,
     texture_bind_group_layout: Option<BindGroupLayout>,
 
-    cur_tex_idx: u32,
+    // Uploaded images to share the same texture between materials.
+    // `None` means the slot is freed and can be reused.
+    images: Vec<Option<Rc<dyn Image>>>,
+
+    // Indices of the render target textures, and the bind groups which replace the texture with
+    // the placeholder, since the texture can't be bound while it is rendered.
+    render_target_indices: Vec<i32>,
+    render_target_bind_groups: Vec<BindGroup>,
+    placeholder_view: TextureView,
+
+    // Whether 32 bit float textures can be uploaded without conversion.
+    float32_filterable: bool,
 }
 
 impl RenderedTexture {
-    fn make_texture(image: &dyn Image, device: &Device, queue: &Queue) -> (Sampler, TextureView) {
+    fn make_texture(
+        image: &dyn Image,
+        float32_filterable: bool,
+        device: &Device,
+        queue: &Queue,
+    ) -> (Sampler, TextureView) {
         let texture = {
             let buf = image.data();
             let width = image.width();
             let height = image.height();
+            let (format, channel, upload_channel) =
+                Self::texture_format(image.format(), device.features(), float32_filterable);
+            let mip_levels = match image.mipmaps() {
+                true => {
+                    let srgb = format == wgpu::TextureFormat::Rgba8UnormSrgb;
+                    mipmap::generate_mip_chain(
+                        buf,
+                        width,
+                        height,
+                        image.bytes_per_pixel(),
+                        channel,
+                        srgb,
+                    )
+                }
+                false => vec![],
+            };
+            // The data is converted if the format is not supported by the device.
+            let bytes_per_pixel =
+                image.bytes_per_pixel() / channel.size() as u32 * upload_channel.size() as u32;
 
             let size = wgpu::Extent3d {
                 width,
@@ -157,48 +374,125 @@ impl RenderedTexture {
             let texture = device.create_texture(&wgpu::TextureDescriptor {
                 label: Some("Texture mesh texture"),
                 size,
-                mip_level_count: 1,
+                mip_level_count: mip_levels.len() as u32 + 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: match image.format() {
-                    TextureFormat::Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
-                    TextureFormat::Rgba16 => wgpu::TextureFormat::Rgba16Unorm,
-                },
+                format,
                 usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
                 view_formats: &[],
             });
-            queue.write_texture(
-                texture.as_image_copy(),
-                buf,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(width * image.bytes_per_pixel()),
-                    rows_per_image: None,
-                },
-                size,
-            );
+            let write_level = |mip_level, data: &[u8], width, height| {
+                let data = match channel == upload_channel {
+                    true => Cow::Borrowed(data),
+                    false => Cow::Owned(texel::convert(data, channel, upload_channel)),
+                };
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        mip_level,
+                        ..texture.as_image_copy()
+                    },
+                    &data,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(width * bytes_per_pixel),
+                        rows_per_image: None,
+                    },
+                    wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            };
+            write_level(0, buf, width, height);
+            for (i, level) in mip_levels.iter().enumerate() {
+                write_level(i as u32 + 1, &level.data, level.width, level.height);
+            }
             texture
         };
 
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: None,
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = Self::make_sampler(&image.sampler(), device);
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         (sampler, view)
     }
 
+    // Returns the format to create the texture, the channel type of the image,
+    // and the channel type to upload. 16 bit normalized and 32 bit float textures fall back to
+    // half float textures if the device can't filter them.
+    pub(super) fn texture_format(
+        format: &TextureFormat,
+        features: Features,
+        float32_filterable: bool,
+    ) -> (wgpu::TextureFormat, ChannelType, ChannelType) {
+        let (format, channel) = match format {
+            TextureFormat::R8 => (wgpu::TextureFormat::R8Unorm, ChannelType::Unorm8),
+            TextureFormat::Rg8 => (wgpu::TextureFormat::Rg8Unorm, ChannelType::Unorm8),
+            TextureFormat::Rgba8 => (wgpu::TextureFormat::Rgba8Unorm, ChannelType::Unorm8),
+            TextureFormat::Rgba8Srgb => (wgpu::TextureFormat::Rgba8UnormSrgb, ChannelType::Unorm8),
+            TextureFormat::Rgba16 if features.contains(Features::TEXTURE_FORMAT_16BIT_NORM) => {
+                (wgpu::TextureFormat::Rgba16Unorm, ChannelType::Unorm16)
+            }
+            TextureFormat::Rgba16 => {
+                return (
+                    wgpu::TextureFormat::Rgba16Float,
+                    ChannelType::Unorm16,
+                    ChannelType::Float16,
+                )
+            }
+            TextureFormat::Rgba32Float if float32_filterable => {
+                (wgpu::TextureFormat::Rgba32Float, ChannelType::Float32)
+            }
+            TextureFormat::Rgba32Float => {
+                return (
+                    wgpu::TextureFormat::Rgba16Float,
+                    ChannelType::Float32,
+                    ChannelType::Float16,
+                )
+            }
+        };
+        (format, channel, channel)
+    }
+
+    pub(super) fn make_sampler(desc: &SamplerDescriptor, device: &Device) -> Sampler {
+        let address_mode = |mode: &AddressMode| match mode {
+            AddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            AddressMode::Repeat => wgpu::AddressMode::Repeat,
+            AddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        };
+        let filter_mode = |mode: &FilterMode| match mode {
+            FilterMode::Nearest => wgpu::FilterMode::Nearest,
+            FilterMode::Linear => wgpu::FilterMode::Linear,
+        };
+        // Anisotropic filtering is valid only with the linear filters.
+        let all_linear = [desc.mag_filter, desc.min_filter, desc.mipmap_filter]
+            .iter()
+            .all(|f| *f == FilterMode::Linear);
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: address_mode(&desc.address_mode_u),
+            address_mode_v: address_mode(&desc.address_mode_v),
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter_mode(&desc.mag_filter),
+            min_filter: filter_mode(&desc.min_filter),
+            mipmap_filter: filter_mode(&desc.mipmap_filter),
+            anisotropy_clamp: match all_linear {
+                true => desc.anisotropy.clamp(1, 16),
+                false => 1,
+            },
+            ..Default::default()
+        })
+    }
+
+    fn make_empty_texture(device: &Device, queue: &Queue) -> (Sampler, TextureView) {
+        let empty = DefaultImage::new(1, 1, TextureFormat::Rgba8, vec![0; 4]);
+        Self::make_texture(&empty, false, device, queue)
+    }
+
     fn make_bind_group(
         device: &Device,
-        texture_view_array: &[TextureView],
+        texture_view_array: &[&TextureView],
         sampler_array: &[Sampler],
     ) -> (BindGroupLayout, BindGroup) {
         let texture_bind_group_layout =
@@ -229,9 +523,7 @@ impl RenderedTexture {
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureViewArray(
-                        &texture_view_array.iter().collect::<Vec<_>>(),
-                    ),
+                    resource: wgpu::BindingResource::TextureViewArray(texture_view_array),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
@@ -246,32 +538,103 @@ impl RenderedTexture {
         (texture_bind_group_layout, texture_bind_group)
     }
 
-    fn update(
-        image: &dyn Image,
+    // Return the index of the texture array if the image is already uploaded.
+    fn find(&self, image: &Rc<dyn Image>) -> Option<i32> {
+        self.images
+            .iter()
+            .position(|i| {
+                matches!(i, Some(i) if Rc::as_ptr(i) as *const u8 == Rc::as_ptr(image) as *const u8)
+            })
+            .map(|idx| idx as i32)
+    }
+
+    // Upload the image if it is not uploaded yet, and return the index of the texture array.
+    fn push(&mut self, image: &Rc<dyn Image>, device: &Device, queue: &Queue) -> i32 {
+        if let Some(idx) = self.find(image) {
+            return idx;
+        }
+
+        let (sampler, view) =
+            Self::make_texture(image.as_ref(), self.float32_filterable, device, queue);
+        self.insert(image, sampler, view)
+    }
+
+    // Register the texture of the render target. The image is used only to identify the texture.
+    pub(super) fn push_render_target(
+        &mut self,
+        image: &Rc<dyn Image>,
+        view: TextureView,
         device: &Device,
-        queue: &Queue,
-        rendered_texture: &mut RenderedTexture,
-    ) {
-        let (sampler, view) = Self::make_texture(image, device, queue);
+    ) -> i32 {
+        let idx = self.insert(image, Self::make_sampler(&image.sampler(), device), view);
+        self.render_target_indices.push(idx);
+        idx
+    }
 
-        let (mut texture_view_array, mut sampler_array) = (vec![view], vec![sampler]);
+    fn insert(&mut self, image: &Rc<dyn Image>, sampler: Sampler, view: TextureView) -> i32 {
+        // Reuse the freed slot to keep the length of the texture array.
+        match self.images.iter().position(|i| i.is_none()) {
+            Some(idx) => {
+                self.texture_view_array[idx] = view;
+                self.sampler_array[idx] = sampler;
+                self.images[idx] = Some(image.clone());
+                idx as i32
+            }
+            None => {
+                self.texture_view_array.push(view);
+                self.sampler_array.push(sampler);
+                self.images.push(Some(image.clone()));
+                (self.images.len() - 1) as i32
+            }
+        }
+    }
 
-        texture_view_array.append(&mut rendered_texture.texture_view_array);
-        sampler_array.append(&mut rendered_texture.sampler_array);
+    // Free the slot by replacing the texture with an empty one.
+    // The length of the texture array is kept, since the pipeline layout depends on it.
+    fn release(&mut self, idx: usize, device: &Device, queue: &Queue) {
+        let (sampler, view) = Self::make_empty_texture(device, queue);
+        self.texture_view_array[idx] = view;
+        self.sampler_array[idx] = sampler;
+        self.images[idx] = None;
+    }
 
+    fn update_bind_group(&mut self, device: &Device) {
         let (texture_bind_group_layout, texture_bind_group) =
-            if !texture_view_array.is_empty() && !sampler_array.is_empty() {
-                let (a, b) = Self::make_bind_group(device, &texture_view_array, &sampler_array);
+            if !self.texture_view_array.is_empty() && !self.sampler_array.is_empty() {
+                let views: Vec<_> = self.texture_view_array.iter().collect();
+                let (a, b) = Self::make_bind_group(device, &views, &self.sampler_array);
                 (Some(a), Some(b))
             } else {
                 (None, None)
             };
 
-        rendered_texture.texture_view_array = texture_view_array;
-        rendered_texture.sampler_array = sampler_array;
+        self.texture_bind_group_layout = texture_bind_group_layout;
+        self.texture_bind_group = texture_bind_group;
+
+        self.render_target_bind_groups = self
+            .render_target_indices
+            .iter()
+            .map(|idx| {
+                let views: Vec<_> = self
+                    .texture_view_array
+                    .iter()
+                    .enumerate()
+                    .map(|(i, view)| match i as i32 == *idx {
+                        true => &self.placeholder_view,
+                        false => view,
+                    })
+                    .collect();
+                Self::make_bind_group(device, &views, &self.sampler_array).1
+            })
+            .collect();
+    }
 
-        rendered_texture.texture_bind_group_layout = texture_bind_group_layout;
-        rendered_texture.texture_bind_group = texture_bind_group;
+    // The bind group which can be used while the texture of `idx` is rendered.
+    fn bind_group_without(&self, idx: i32) -> Option<&BindGroup> {
+        match self.render_target_indices.iter().position(|i| *i == idx) {
+            Some(pos) => self.render_target_bind_groups.get(pos),
+            None => self.texture_bind_group.as_ref(),
+        }
     }
 }
 
@@ -279,12 +642,19 @@ impl RenderedTexture {
 pub(super) struct DynamicRenderer {
     pub(super) rendered_entity: RenderedEntity,
     pub(super) rendered_texture: RenderedTexture,
+    // Ordered as same as `Scene::render_targets`.
+    pub(super) rendered_targets: Vec<RenderedTarget>,
     pub(super) device: Device,
     pub(super) queue: Queue,
 }
 
 impl DynamicRenderer {
-    pub fn new(device: Device, queue: Queue, renderer_builder: &mut RendererBuilder) -> Self {
+    pub fn new(
+        device: Device,
+        queue: Queue,
+        renderer_builder: &mut RendererBuilder,
+        scene: &Scene,
+    ) -> Self {
         let entity_length = renderer_builder.mesh_length();
         let (entity_uniform_size, entity_uniform_buf, entity_uniform_alignment) =
             RenderedEntity::make_uniform(
@@ -293,38 +663,47 @@ impl DynamicRenderer {
                 mem::size_of::<EntityUniformBuffer>() as wgpu::BufferAddress,
             );
 
-        let mut texture_view_array = vec![];
-        let mut sampler_array = vec![];
+        let mut rendered_texture = RenderedTexture {
+            texture_view_array: vec![],
+            sampler_array: vec![],
+            texture_bind_group_layout: None,
+            texture_bind_group: None,
+            images: vec![],
+            render_target_indices: vec![],
+            render_target_bind_groups: vec![],
+            placeholder_view: RenderedTexture::make_empty_texture(&device, &queue).1,
+            float32_filterable: scene.config.float32_filterable,
+        };
+        // Render targets are registered first to bind the textures before the entities use them.
+        let rendered_targets = scene
+            .scene
+            .render_targets
+            .iter()
+            .map(|style| {
+                RenderedTarget::new(
+                    &device,
+                    style,
+                    scene,
+                    scene.config.color_format,
+                    Renderer::DEPTH_FORMAT,
+                    &mut rendered_texture,
+                )
+            })
+            .collect();
         let mut i = 0;
-        let mut tex_idx = 0;
         let (entities, meta_list) = Self::create_recursive_entity(
             &device,
             &queue,
             std::mem::take(&mut renderer_builder.entities),
-            (&mut texture_view_array, &mut sampler_array),
-            (&mut i, &mut tex_idx),
+            &mut rendered_texture,
+            &mut i,
             entity_uniform_alignment,
         );
+        rendered_texture.update_bind_group(&device);
 
         let (entity_bind_group_layout, entity_bind_group) =
             RenderedEntity::make_bind_group(&device, entity_uniform_size, &entity_uniform_buf);
-
-        let (texture_bind_group_layout, texture_bind_group) =
-            if !texture_view_array.is_empty() && !sampler_array.is_empty() {
-                let (a, b) =
-                    RenderedTexture::make_bind_group(&device, &texture_view_array, &sampler_array);
-                (Some(a), Some(b))
-            } else {
-                (None, None)
-            };
-
-        let rendered_texture = RenderedTexture {
-            texture_view_array,
-            sampler_array,
-            texture_bind_group_layout,
-            texture_bind_group,
-            cur_tex_idx: tex_idx,
-        };
+        let default_instance_buf = RenderedEntity::make_default_instance_buffer(&device);
 
         DynamicRenderer {
             device,
@@ -333,10 +712,14 @@ impl DynamicRenderer {
                 entities,
                 meta_list,
                 entity_uniform_buf,
+                entity_uniform_alignment,
                 entity_bind_group,
                 entity_bind_group_layout,
+                default_instance_buf,
+                dirty: true,
             },
             rendered_texture,
+            rendered_targets,
         }
     }
 
@@ -344,8 +727,8 @@ impl DynamicRenderer {
         device: &Device,
         queue: &Queue,
         descriptors: Vec<EntityDescriptor>,
-        (texture_view_array, sampler_array): (&mut Vec<TextureView>, &mut Vec<Sampler>),
-        (idx, tex_idx): (&mut u64, &mut u32),
+        rendered_texture: &mut RenderedTexture,
+        idx: &mut u64,
         entity_uniform_alignment: u64,
     ) -> (Vec<Entity>, Vec<Option<RenderedEntityMeta>>) {
         let mut entities = vec![];
@@ -353,76 +736,71 @@ impl DynamicRenderer {
         for EntityDescriptor {
             id,
             mesh,
-            fill_color,
             transform,
+            material,
             state,
             children,
-            reflection,
-            texture,
-            normal_map,
-            receive_shadow,
+            instances,
         } in descriptors.into_iter()
         {
-            let (tex_idx_for_entity, normal_map_idx) = match mesh {
+            let texture_indices = match mesh {
                 Some(mesh) => {
-                    let vertex = match normal_map {
-                        Some(_) => mesh.as_ref().as_tangent_space(),
-                        None => mesh.as_ref().vertex(),
+                    let use_tangent_space = material.texture(TextureSlot::Normal).is_some();
+                    let vertex = match use_tangent_space {
+                        true => mesh.as_ref().as_tangent_space(),
+                        false => mesh.as_ref().vertex(),
                     };
+                    let index = RenderedEntity::mesh_index(mesh.as_ref());
+                    let bounding = BoundingSphere::from_vertices(vertex.borrow().as_slice());
                     let (vertex_buf, index_buf, vertex_length) = RenderedEntity::make_entity(
                         vertex.borrow().as_slice(),
-                        mesh.as_ref().index(),
+                        index.map(|(index, _, _)| index),
                         device,
                     );
-
-                    let tex_idx_for_entity = if let Some(texture) = &texture {
-                        let (sampler, view) =
-                            RenderedTexture::make_texture(texture.as_ref(), device, queue);
-
-                        texture_view_array.push(view);
-                        sampler_array.push(sampler);
-
-                        let idx = *tex_idx as i32;
-
-                        *tex_idx += 1;
-
-                        Some(idx)
-                    } else {
-                        None
-                    };
-
-                    let normal_map_idx = if let Some(normal_map) = &normal_map {
-                        let (sampler, view) =
-                            RenderedTexture::make_texture(normal_map.as_ref(), device, queue);
-
-                        texture_view_array.push(view);
-                        sampler_array.push(sampler);
-
-                        let idx: i32 = *tex_idx as i32;
-
-                        *tex_idx += 1;
-
-                        Some(idx)
-                    } else {
-                        None
-                    };
-
-                    meta_list.push(Some(RenderedEntityMeta {
+                    let (index_format, index_length) = index
+                        .map_or((IndexFormat::Uint16, 0), |(_, format, length)| {
+                            (format, length)
+                        });
+
+                    // Storing all texture(includes the map) into single texture array
+                    // and access by using the index.
+                    let texture_indices = material
+                        .textures
+                        .iter()
+                        .map(|(slot, image)| (*slot, rendered_texture.push(image, device, queue)))
+                        .collect();
+
+                    let instance_data: Vec<_> = instances
+                        .iter()
+                        .map(InstanceBuffer::from_instance)
+                        .collect();
+                    let mut meta = RenderedEntityMeta {
                         uniform_offset: *idx * entity_uniform_alignment,
                         vertex_buf,
                         index_buf,
                         vertex_length,
-                        index_length: mesh.index().map_or(0, |i| i.len()) as u32,
-                    }));
+                        index_length,
+                        index_format,
+                        mesh,
+                        use_tangent_space,
+                        instance_buf: RenderedEntity::make_instance_buffer(&instance_data, device),
+                        instance_length: instances.len().max(1) as u32,
+                        instances: instance_data,
+                        bounding,
+                        instance_bounding: None,
+                        needs_upload: true,
+                    };
+                    meta.update_instance_bounding();
+                    meta_list.push(Some(meta));
 
                     // Must update only when mesh is exist
                     *idx += 1;
 
-                    (tex_idx_for_entity, normal_map_idx)
+                    texture_indices
                 }
                 None => {
                     meta_list.push(None);
-                    (None, None)
+                    HashMap::new()
                 }
             };
 
@@ -430,139 +808,60 @@ impl DynamicRenderer {
                 device,
                 queue,
                 children,
-                (texture_view_array, sampler_array),
-                (idx, tex_idx),
+                rendered_texture,
+                idx,
                 entity_uniform_alignment,
             );
 
             meta_list.append(&mut meta_list2);
 
-            // Storing all texture(includes the map) into single texture array
-            // and access by using the index.
             entities.push(Entity {
                 id,
-                fill_color,
                 transform,
+                material,
                 state,
-                reflection,
                 children,
-                tex_idx: tex_idx_for_entity,
-                normal_map_idx,
-                receive_shadow,
+                instances,
+                texture_indices,
+                dirty: true,
+                textures_dirty: false,
+                world_transform: Transform::default(),
             });
         }
 
         (entities, meta_list)
     }
 
-    fn update_recursive_entity(
-        &mut self,
-        descriptors: Vec<EntityDescriptor>,
-        idx: &u64,
-        entity_uniform_alignment: &u64,
-    ) -> (Vec<Entity>, Vec<Option<RenderedEntityMeta>>) {
-        let mut idx = *idx;
-
-        let mut entities = vec![];
-        let mut meta_list = vec![];
-        for EntityDescriptor {
-            id,
-            mesh,
-            fill_color,
-            transform,
-            state,
-            children,
-            reflection,
-            texture,
-            normal_map,
-            receive_shadow,
-        } in descriptors.into_iter()
-        {
-            let (tex_idx_for_entity, normal_map_idx) = match mesh {
-                Some(mesh) => {
-                    let vertex = match normal_map {
-                        Some(_) => mesh.as_ref().as_tangent_space(),
-                        None => mesh.as_ref().vertex(),
-                    };
-                    let (vertex_buf, index_buf, vertex_length) = RenderedEntity::make_entity(
-                        vertex.borrow().as_slice(),
-                        mesh.as_ref().index(),
-                        &self.device,
-                    );
-
-                    let tex_idx = if let Some(texture) = &texture {
-                        RenderedTexture::update(
-                            texture.as_ref(),
-                            &self.device,
-                            &self.queue,
-                            &mut self.rendered_texture,
-                        );
-
-                        let tex_idx = self.rendered_texture.cur_tex_idx;
-                        self.rendered_texture.cur_tex_idx += 1;
-
-                        Some(tex_idx as i32)
-                    } else {
-                        None
-                    };
-
-                    let normal_map_idx = if let Some(normal_map) = &normal_map {
-                        // FIXME(@keiya01): Handle image error
-                        RenderedTexture::update(
-                            normal_map.as_ref(),
-                            &self.device,
-                            &self.queue,
-                            &mut self.rendered_texture,
-                        );
-
-                        let tex_idx = self.rendered_texture.cur_tex_idx;
-                        self.rendered_texture.cur_tex_idx += 1;
-
-                        Some((tex_idx + 1) as i32)
-                    } else {
-                        None
-                    };
-
-                    meta_list.push(Some(RenderedEntityMeta {
-                        uniform_offset: idx * entity_uniform_alignment,
-                        vertex_buf,
-                        index_buf,
-                        vertex_length,
-                        index_length: mesh.index().map_or(0, |i| i.len()) as u32,
-                    }));
-                    // Must update only when mesh is exist
-                    idx += 1;
-
-                    (tex_idx, normal_map_idx)
-                }
-                None => {
-                    meta_list.push(None);
-                    (None, None)
-                }
-            };
-
-            let (children, mut meta_list2) =
-                self.update_recursive_entity(children, &idx, entity_uniform_alignment);
-
-            meta_list.append(&mut meta_list2);
-
-            entities.push(Entity {
-                id,
-                fill_color,
-                transform,
-                state,
-                reflection,
-                children,
-                tex_idx: tex_idx_for_entity,
-                normal_map_idx,
-                receive_shadow,
-            });
+    // Free the textures of `indices` which are not used by any entity anymore.
+    // Returns true if any texture is freed. The bind group must be updated after that.
+    fn release_unused_textures(&mut self, mut indices: Vec<i32>) -> bool {
+        let mut used = vec![];
+        collect_texture_indices(&self.rendered_entity.entities, &mut used);
+        used.extend(&self.rendered_texture.render_target_indices);
+        indices.retain(|idx| !used.contains(idx));
+        indices.sort();
+        indices.dedup();
+        for idx in indices.iter() {
+            self.rendered_texture
+                .release(*idx as usize, &self.device, &self.queue);
         }
-
-        (entities, meta_list)
+        !indices.is_empty()
     }
 }
 
+/// The result of `Renderer::pick`.
+#[derive(Debug, Clone)]
+pub struct PickResult {
+    /// The id of the picked entity.
+    pub id: String,
+    /// The hit point in world space.
+    pub point: Vec3,
+    /// The normal of the hit triangle in world space. This always faces the camera.
+    pub normal: Vec3,
+    /// The id of the view which the position is in. `None` is the main camera.
+    pub view_id: Option<String>,
+}
+
 // The struct is immutable basically.
 pub struct Renderer {
     pub(super) dynamic_renderer: DynamicRenderer,
@@ -572,6 +871,9 @@ pub struct Renderer {
     background: [f64; 4],
     render_pipelines: HashMap<EntityRendererState, RenderPipeline>,
     shadow_baker: ShadowBaker,
+    clear_pipelines: ClearPipelines,
+    skybox: Skybox,
+    post_processor: Option<PostProcessor>,
 
     dst_texture: Option<Texture>,
 }
@@ -582,18 +884,21 @@ impl Renderer {
         &self.dynamic_renderer.rendered_entity.entities
     }
 
+    /// The changed entities are found in the next rendering, so avoid calling this every frame
+    /// if the entities are not changed.
     pub fn entities_mut(&mut self) -> &mut [Entity] {
+        self.dynamic_renderer.rendered_entity.dirty = true;
         &mut self.dynamic_renderer.rendered_entity.entities
     }
 
-    // FIXME(@kaiye01): Support updating ShadowBaker when entity is added.
     pub fn push_entity(&mut self, descriptor: EntityDescriptor) {
         let entity_length = count_some(&self.dynamic_renderer.rendered_entity.meta_list);
+        let descriptor_mesh_length = descriptor.flatten_mesh_length();
         let (entity_uniform_size, entity_uniform_buf, entity_uniform_alignment) =
             RenderedEntity::make_uniform(
                 &self.dynamic_renderer.device,
                 // Length of `Some` of meta_list will be equal with entity mesh length.
-                entity_length + descriptor.flatten_mesh_length(),
+                entity_length + descriptor_mesh_length,
                 mem::size_of::<EntityUniformBuffer>() as wgpu::BufferAddress,
             );
 
@@ -603,18 +908,44 @@ impl Renderer {
             &entity_uniform_buf,
         );
 
-        let idx = entity_length as u64;
-        let (mut entities, mut metas) = self.dynamic_renderer.update_recursive_entity(
+        let mut idx = entity_length as u64;
+        let dynamic_renderer = &mut self.dynamic_renderer;
+        let (mut entities, mut metas) = DynamicRenderer::create_recursive_entity(
+            &dynamic_renderer.device,
+            &dynamic_renderer.queue,
             vec![descriptor],
-            &idx,
-            &entity_uniform_alignment,
+            &mut dynamic_renderer.rendered_texture,
+            &mut idx,
+            entity_uniform_alignment,
         );
+        dynamic_renderer
+            .rendered_texture
+            .update_bind_group(&dynamic_renderer.device);
 
         self.dynamic_renderer
             .rendered_entity
             .entities
             .append(&mut entities);
         self.dynamic_renderer.rendered_entity.entity_uniform_buf = entity_uniform_buf;
+        self.shadow_baker.entity.resize(
+            &self.dynamic_renderer.device,
+            entity_length + descriptor_mesh_length,
+            entity_uniform_alignment,
+        );
+        // All uniforms need to be uploaded into the new buffer.
+        for meta in self
+            .dynamic_renderer
+            .rendered_entity
+            .meta_list
+            .iter_mut()
+            .flatten()
+        {
+            meta.needs_upload = true;
+        }
+        self.dynamic_renderer.rendered_entity.dirty = true;
+        self.dynamic_renderer
+            .rendered_entity
+            .entity_uniform_alignment = entity_uniform_alignment;
         self.dynamic_renderer
             .rendered_entity
             .entity_bind_group_layout = entity_bind_group_layout;
@@ -625,6 +956,183 @@ impl Renderer {
             .append(&mut metas);
     }
 
+    /// Remove the entity and its children from the renderer.
+    pub fn remove_entity(&mut self, id: &str) -> Option<Entity> {
+        let dynamic_renderer = &mut self.dynamic_renderer;
+        let rendered_entity = &mut dynamic_renderer.rendered_entity;
+
+        let mut start = 0;
+        let entity = remove_entity_recursive(&mut rendered_entity.entities, id, &mut start)?;
+
+        // `meta_list` is ordered as same as `traverse_entities_with_transform`,
+        // so the entity and its children are placed continuously.
+        let mut len = 0;
+        traverse_entities_with_transform(
+            std::slice::from_ref(&entity),
+            &Transform::default(),
+            &mut |_, _| len += 1,
+        );
+        rendered_entity.meta_list.drain(start..start + len);
+        rendered_entity.compact_uniform_offsets();
+
+        // Shrink the shadow uniform buffer, and upload all uniforms into the new buffer.
+        self.shadow_baker.entity.resize(
+            &dynamic_renderer.device,
+            count_some(&rendered_entity.meta_list),
+            rendered_entity.entity_uniform_alignment,
+        );
+        for meta in rendered_entity.meta_list.iter_mut().flatten() {
+            meta.needs_upload = true;
+        }
+        rendered_entity.dirty = true;
+
+        // Free the textures which are not used by the other entities anymore.
+        let mut removed = vec![];
+        collect_texture_indices(std::slice::from_ref(&entity), &mut removed);
+        if dynamic_renderer.release_unused_textures(removed) {
+            dynamic_renderer
+                .rendered_texture
+                .update_bind_group(&dynamic_renderer.device);
+        }
+
+        Some(entity)
+    }
+
+    /// Re-upload the vertices and indices of the entity mesh.
+    /// Call this after mutating the vertices returned by `Mesh::vertex`.
+    /// Do nothing if the entity is not found or doesn't have a mesh.
+    pub fn update_mesh(&mut self, id: &str) {
+        let dynamic_renderer = &mut self.dynamic_renderer;
+        let rendered_entity = &mut dynamic_renderer.rendered_entity;
+
+        let mut pos = 0;
+        if !find_entity_position(&rendered_entity.entities, id, &mut pos) {
+            return;
+        }
+        if let Some(Some(meta)) = rendered_entity.meta_list.get_mut(pos) {
+            RenderedEntity::update_entity(meta, &dynamic_renderer.device, &dynamic_renderer.queue);
+        }
+    }
+
+    /// Pick the nearest entity at the position of the screen by casting a ray from the camera.
+    /// `x` and `y` are the physical pixels from the top-left of the render target.
+    /// The ray is cast from the topmost view which contains the position, or the main camera.
+    /// Only the entities drawn as `Topology::TriangleList` are picked.
+    /// This never panics. The triangles are read from the current vertices of the mesh, so
+    /// the triangles whose indices are out of range are skipped, e.g. the vertices are removed
+    /// before `update_mesh` is called. The entities whose vertices are mutably borrowed are skipped.
+    pub fn pick(&self, x: f32, y: f32) -> Option<PickResult> {
+        let (width, height) = (self.config.width, self.config.height);
+        let scene = &self.scene.scene;
+        // Views are drawn over the main camera in ascending order.
+        let mut views: Vec<_> = scene.views.iter().collect();
+        views.sort_by_key(|view| *view.order());
+        let (view_id, camera, viewport) = views
+            .iter()
+            .rev()
+            .find(|view| view.viewport().contains(width, height, x, y))
+            .map(|view| (Some(view.id().clone()), view.camera(), *view.viewport()))
+            .or_else(|| {
+                scene.viewport.contains(width, height, x, y).then_some((
+                    None,
+                    &scene.camera,
+                    scene.viewport,
+                ))
+            })?;
+
+        // The viewport is not empty since it contains the position.
+        let (vx, vy, vw, vh) = viewport.to_pixels(width, height);
+        let ray = Ray::from_ndc(
+            (x - vx as f32) / vw as f32 * 2. - 1.,
+            1. - (y - vy as f32) / vh as f32 * 2.,
+            &camera.transform(),
+        );
+        let rendered_entity = &self.dynamic_renderer.rendered_entity;
+
+        let mut nearest: Option<(f32, PickResult)> = None;
+        let mut metas = rendered_entity.meta_list.iter();
+        traverse_entities_with_transform(
+            &rendered_entity.entities,
+            &Transform::default(),
+            &mut |entity, transform| {
+                let meta = match metas.next() {
+                    Some(Some(meta)) => meta,
+                    _ => return,
+                };
+                if entity.state.topology != Topology::TriangleList {
+                    return;
+                }
+
+                let transforms = match entity.instances.is_empty() {
+                    true => vec![transform.clone()],
+                    false => entity
+                        .instances
+                        .iter()
+                        .map(|instance| transform.mul(&instance.transform))
+                        .collect(),
+                };
+
+                let vertices = meta.mesh.vertex();
+                let vertices = match vertices.try_borrow() {
+                    Ok(vertices) => vertices,
+                    Err(_) => return,
+                };
+                let indices: Vec<u32> = match (meta.mesh.index_u32(), meta.mesh.index()) {
+                    (Some(index), _) => index.to_vec(),
+                    (None, Some(index)) => index.iter().map(|i| *i as u32).collect(),
+                    (None, None) => (0..vertices.len() as u32).collect(),
+                };
+
+                for transform in transforms {
+                    let hit_bounding = matches!(
+                        &meta.bounding,
+                        Some(bounding) if ray.intersects_sphere(&bounding.transform(&transform))
+                    );
+                    if !hit_bounding {
+                        continue;
+                    }
+
+                    let mat = transform.as_mat4();
+                    for triangle in indices.chunks_exact(3) {
+                        let [a, b, c] = match [triangle[0], triangle[1], triangle[2]]
+                            .map(|i| vertices.get(i as usize))
+                        {
+                            [Some(a), Some(b), Some(c)] => [a, b, c].map(|vertex| {
+                                let pos = vertex.pos;
+                                mat.transform_point3(glam::Vec3::new(pos[0], pos[1], pos[2]))
+                            }),
+                            _ => continue,
+                        };
+                        let distance = match ray.intersect_triangle(a, b, c) {
+                            Some(distance) => distance,
+                            None => continue,
+                        };
+                        if matches!(&nearest, Some((nearest, _)) if *nearest <= distance) {
+                            continue;
+                        }
+
+                        let mut normal = (b - a).cross(c - a).normalize();
+                        if normal.dot(ray.direction) > 0. {
+                            normal = -normal;
+                        }
+                        let point = ray.at(distance);
+                        nearest = Some((
+                            distance,
+                            PickResult {
+                                id: entity.id.clone(),
+                                point: Vec3::from_array(&point.to_array()),
+                                normal: Vec3::from_array(&normal.to_array()),
+                                view_id: view_id.clone(),
+                            },
+                        ));
+                    }
+                }
+            },
+        );
+
+        nearest.map(|(_, result)| result)
+    }
+
     pub fn scene(&self) -> &crate::scene::Scene {
         &self.scene.scene
     }
@@ -641,9 +1149,18 @@ impl Renderer {
     pub fn new<
         W: raw_window_handle::HasRawWindowHandle + raw_window_handle::HasRawDisplayHandle,
     >(
-        mut renderer_builder: RendererBuilder,
+        renderer_builder: RendererBuilder,
         window: Option<&W>,
     ) -> Self {
+        Self::try_new(renderer_builder, window).expect("Failed to create renderer")
+    }
+
+    pub fn try_new<
+        W: raw_window_handle::HasRawWindowHandle + raw_window_handle::HasRawDisplayHandle,
+    >(
+        mut renderer_builder: RendererBuilder,
+        window: Option<&W>,
+    ) -> Result<Self, RendererError> {
         let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
         let dx12_shader_compiler = wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default();
 
@@ -652,14 +1169,16 @@ impl Renderer {
             dx12_shader_compiler,
         });
 
-        let surface = window.map(|w| unsafe { instance.create_surface(w) }.unwrap());
+        let surface = window
+            .map(|w| unsafe { instance.create_surface(w) })
+            .transpose()?;
         let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(),
             force_fallback_adapter: false,
             // Request an adapter which can render to our surface
             compatible_surface: surface.as_ref(),
         }))
-        .expect("Failed to find an appropriate adapter");
+        .ok_or(RendererError::AdapterNotFound)?;
 
         let adapter_features = if renderer_builder
             .renderer_specific_attributes
@@ -674,21 +1193,28 @@ impl Renderer {
         // TODO: Use constant variable to reduce group.
         limits.max_bind_groups = 5;
         // Create the logical device and command queue
+        // Upload the textures at their precision if possible.
+        let mut texture_features = Features::TEXTURE_FORMAT_16BIT_NORM;
+        if is_float32_filterable(&adapter) {
+            texture_features |= Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        }
+        let texture_features = adapter.features() & texture_features;
         let (device, queue) = pollster::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                features: adapter_features | renderer_builder.renderer_specific_attributes.features,
+                features: adapter_features
+                    | texture_features
+                    | renderer_builder.renderer_specific_attributes.features,
                 // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
                 limits,
             },
             None,
-        ))
-        .expect("Failed to create device");
+        ))?;
 
         let config = if let Some(surface) = &surface {
             surface
                 .get_default_config(&adapter, renderer_builder.width, renderer_builder.height)
-                .expect("Surface isn't supported by the adapter.")
+                .ok_or(RendererError::UnsupportedSurface)?
         } else {
             SurfaceConfiguration {
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -712,8 +1238,16 @@ impl Renderer {
             &config,
         );
 
+        // Create the pipelines for the states of the entities in addition to the specified states.
+        let mut states: Vec<_> = renderer_builder
+            .states
+            .iter()
+            .map(|s| EntityRendererState::from_renderer_state(*s))
+            .collect();
+        EntityDescriptor::collect_states(&renderer_builder.entities, &mut states);
+
         let mesh_length = renderer_builder.mesh_length();
-        let dynamic_renderer = DynamicRenderer::new(device, queue, &mut renderer_builder);
+        let dynamic_renderer = DynamicRenderer::new(device, queue, &mut renderer_builder, &scene);
 
         // Load the shaders from disk
         let mut shaders: Option<Rc<ShaderModule>> = None;
@@ -764,9 +1298,7 @@ impl Renderer {
             .is_some();
 
         let mut render_pipelines = HashMap::new();
-        let states = renderer_builder.states.clone();
-        for state in states {
-            let key = EntityRendererState::from_renderer_state(state);
+        for key in states.iter().cloned() {
             if render_pipelines.get(&key).is_some() {
                 continue;
             }
@@ -777,6 +1309,7 @@ impl Renderer {
                 ProcessOption {
                     has_texture: has_tex,
                     max_light_num: scene.scene.max_light_num,
+                    cube_array: scene.shadow_uniform.cube_array,
                 },
             );
 
@@ -791,16 +1324,41 @@ impl Renderer {
                         vertex: wgpu::VertexState {
                             module: &shader,
                             entry_point: "vs_main",
-                            buffers: &[VertexBufferLayout {
-                                array_stride: vertex_buf_size,
-                                step_mode: wgpu::VertexStepMode::Vertex,
-                                attributes: &vertex_buf_attr,
-                            }],
+                            buffers: &[
+                                VertexBufferLayout {
+                                    array_stride: vertex_buf_size,
+                                    step_mode: wgpu::VertexStepMode::Vertex,
+                                    attributes: &vertex_buf_attr,
+                                },
+                                InstanceBuffer::layout(&InstanceBuffer::ATTRIBUTES),
+                            ],
                         },
                         fragment: Some(wgpu::FragmentState {
                             module: &shader,
                             entry_point: "fs_main",
-                            targets: &[Some(config.format.into())],
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format: scene.config.color_format,
+                                blend: match &key.blend {
+                                    BlendMode::Opaque => None,
+                                    BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+                                    BlendMode::Additive => Some(wgpu::BlendState {
+                                        color: wgpu::BlendComponent {
+                                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                                            dst_factor: wgpu::BlendFactor::One,
+                                            operation: wgpu::BlendOperation::Add,
+                                        },
+                                        alpha: wgpu::BlendComponent {
+                                            src_factor: wgpu::BlendFactor::One,
+                                            dst_factor: wgpu::BlendFactor::One,
+                                            operation: wgpu::BlendOperation::Add,
+                                        },
+                                    }),
+                                    BlendMode::Premultiplied => {
+                                        Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+                                    }
+                                },
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
                         }),
                         primitive: wgpu::PrimitiveState {
                             topology: match &key.topology {
@@ -808,8 +1366,15 @@ impl Renderer {
                                 Topology::LineList => PrimitiveTopology::LineList,
                                 Topology::TriangleList => PrimitiveTopology::TriangleList,
                             },
-                            front_face: wgpu::FrontFace::Ccw,
-                            cull_mode: Some(wgpu::Face::Back),
+                            front_face: match &key.front_face {
+                                FrontFace::Ccw => wgpu::FrontFace::Ccw,
+                                FrontFace::Cw => wgpu::FrontFace::Cw,
+                            },
+                            cull_mode: match &key.cull_mode {
+                                CullMode::None => None,
+                                CullMode::Front => Some(wgpu::Face::Front),
+                                CullMode::Back => Some(wgpu::Face::Back),
+                            },
                             polygon_mode: match &key.polygon_mode {
                                 PolygonMode::Fill => wgpu::PolygonMode::Fill,
                                 PolygonMode::Line => wgpu::PolygonMode::Line,
@@ -819,7 +1384,8 @@ impl Renderer {
                         },
                         depth_stencil: Some(wgpu::DepthStencilState {
                             format: Self::DEPTH_FORMAT,
-                            depth_write_enabled: true,
+                            // Transparent entities are tested with the depth of opaque entities, but don't write it.
+                            depth_write_enabled: key.blend == BlendMode::Opaque,
                             depth_compare: wgpu::CompareFunction::Less,
                             stencil: wgpu::StencilState::default(),
                             bias: wgpu::DepthBiasState::default(),
@@ -836,26 +1402,59 @@ impl Renderer {
         let shadow_baker = ShadowBaker::new(
             &dynamic_renderer.device,
             mesh_length,
+            dynamic_renderer.rendered_entity.entity_uniform_alignment,
             &scene,
-            renderer_builder.states,
+            states,
         );
 
-        let mut renderer = Self {
-            dynamic_renderer,
-            config,
+        let clear_pipelines = ClearPipelines::new(
+            &dynamic_renderer.device,
+            scene.config.color_format,
+            Self::DEPTH_FORMAT,
+            scene.config.max_samples.min(scene.scene.msaa_samples),
+        );
+
+        // The fog tints the background even without the skybox.
+        // The pipeline is always created, since the fog can be enabled after the creation.
+        let skybox = Skybox::new(
+            &dynamic_renderer.device,
+            &dynamic_renderer.queue,
+            scene.scene.skybox.as_ref(),
+            &scene,
+            scene.config.color_format,
+            Self::DEPTH_FORMAT,
+        );
+
+        let post_processor = scene.scene.post_process.as_ref().map(|style| {
+            PostProcessor::new(
+                &dynamic_renderer.device,
+                style,
+                config.format,
+                config.width,
+                config.height,
+            )
+        });
+
+        let mut renderer = Self {
+            dynamic_renderer,
+            config,
             surface,
             scene,
             background: rgba_to_array_64(&renderer_builder.background),
             render_pipelines,
             shadow_baker,
+            clear_pipelines,
+            skybox,
+            post_processor,
             dst_texture: None,
         };
+        renderer.fit_cameras();
 
         if renderer_builder.enable_forward_depth {
             renderer.set_depth_texture();
         }
 
-        renderer
+        Ok(renderer)
     }
 
     fn set_depth_texture(&mut self) {
@@ -893,24 +1492,201 @@ impl Renderer {
         if let Some(s) = self.surface.as_ref() {
             s.configure(&self.dynamic_renderer.device, &self.config)
         }
-        self.scene.scene.camera.set_width(width as f32);
-        self.scene.scene.camera.set_height(height as f32);
+        if let Some(post_processor) = &mut self.post_processor {
+            post_processor.resize(&self.dynamic_renderer.device, width, height);
+        }
+        self.fit_cameras();
         self.scene.update_scene(&self.dynamic_renderer.queue);
 
         self.set_depth_texture();
     }
 
+    // Fit the aspect of the cameras to their viewports.
+    // This is called in each frame, since the viewports can be changed after the creation.
+    // The cameras are touched only when the size is changed, not to recompute their uniforms.
+    fn fit_cameras(&mut self) {
+        let (width, height) = (self.config.width, self.config.height);
+        let scene = &mut self.scene.scene;
+        let (_, _, w, h) = scene.viewport.to_pixels(width, height);
+        if (scene.camera().width, scene.camera().height) != (w as f32, h as f32) {
+            scene.camera_mut().set_width(w as f32);
+            scene.camera_mut().set_height(h as f32);
+        }
+        for view in scene.views.iter_mut() {
+            let (_, _, w, h) = view.viewport().to_pixels(width, height);
+            if (view.camera().width, view.camera().height) != (w as f32, h as f32) {
+                view.camera_mut().set_width(w as f32);
+                view.camera_mut().set_height(h as f32);
+            }
+        }
+    }
+
     fn update_scene(&mut self) {
-        // TODO: Invoke it only when camera is changed
+        self.fit_cameras();
         self.scene.update_scene(&self.dynamic_renderer.queue);
-        // TODO: Invoke it only when light is changed
-        self.scene.update_light(&self.dynamic_renderer.queue);
+        self.scene
+            .update_light(&self.dynamic_renderer.device, &self.dynamic_renderer.queue);
+        if let (Some(post_processor), Some(style)) =
+            (&mut self.post_processor, &self.scene.scene.post_process)
+        {
+            post_processor.update(
+                &self.dynamic_renderer.queue,
+                style,
+                self.config.width,
+                self.config.height,
+            );
+        }
+        for (target, style) in self
+            .dynamic_renderer
+            .rendered_targets
+            .iter_mut()
+            .zip(&self.scene.scene.render_targets)
+        {
+            target.scene_uniform.update_camera(
+                &self.dynamic_renderer.queue,
+                style.camera(),
+                style.generation,
+                &self.scene.scene,
+            );
+        }
     }
 
-    fn render_actual(&mut self, view: TextureView) {
-        self.update_scene();
+    // Draw the depth of the entities from the light into the layer of the shadow map.
+    // `slot` is the index of the projection in the camera uniform of the shadow baker.
+    fn bake_shadow(
+        &self,
+        encoder: &mut CommandEncoder,
+        target: ShadowTarget,
+        slot: u32,
+        light_transform: Mat4,
+    ) {
+        let rendered_entity = &self.dynamic_renderer.rendered_entity;
+        let render_pipelines = target.render_pipelines;
+        // The moments of the depth at the far plane, which are `evsm_moments(1.)` in the shader.
+        let (positive, negative) = (5f64.exp(), -(-5f64).exp());
+        let color_attachments =
+            [target
+                .variance_view
+                .map(|view| wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: positive,
+                            g: positive * positive,
+                            b: negative,
+                            a: negative * negative,
+                        }),
+                        store: true,
+                    },
+                })];
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: match target.variance_view {
+                Some(_) => &color_attachments,
+                None => &[],
+            },
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        if let Some((width, height)) = target.map_size {
+            rpass.set_viewport(0., 0., width as f32, height as f32, 0., 1.);
+        }
+
+        // Set shadow projection dynamically
+        {
+            let size = mem::size_of::<[f32; 16]>() as wgpu::BufferAddress;
+            let uniform_alignment = {
+                let alignment = self
+                    .dynamic_renderer
+                    .device
+                    .limits()
+                    .min_uniform_buffer_offset_alignment
+                    as wgpu::BufferAddress;
+                align_to(size, alignment)
+            };
+            let offset = slot as wgpu::BufferAddress * uniform_alignment;
+
+            self.shadow_baker
+                .camera
+                .update(&self.dynamic_renderer.queue, light_transform, offset);
+
+            rpass.set_bind_group(0, &self.shadow_baker.camera.bind_group, &[offset as u32]);
+        }
+        let frustum = Frustum::from_mat4(&light_transform);
 
+        let mut i = 0;
+        traverse_entities(&rendered_entity.entities, &mut |entity| {
+            let meta = rendered_entity.meta_list.get(i);
+
+            i += 1;
+
+            if let Some(Some(meta)) = meta {
+                if !meta.is_visible(&frustum, &entity.world_transform) {
+                    return;
+                }
+
+                // The states are validated before rendering, so the entity is skipped just in case.
+                let pipeline = match render_pipelines.get(&entity.state) {
+                    Some(pipeline) => pipeline,
+                    None => return,
+                };
+                rpass.set_pipeline(pipeline);
+
+                rpass.set_bind_group(
+                    1,
+                    &self.shadow_baker.entity.entity_bind_group,
+                    &[meta.uniform_offset as u32],
+                );
+
+                rpass.set_vertex_buffer(0, meta.vertex_buf.slice(..));
+                rpass.set_vertex_buffer(
+                    1,
+                    meta.instance_buf
+                        .as_ref()
+                        .unwrap_or(&rendered_entity.default_instance_buf)
+                        .slice(..),
+                );
+                match &meta.index_buf {
+                    Some(index_buf) => {
+                        rpass.set_index_buffer(index_buf.slice(..), meta.index_format);
+                        rpass.draw_indexed(0..meta.index_length, 0, 0..meta.instance_length);
+                    }
+                    None => rpass.draw(0..meta.vertex_length, 0..meta.instance_length),
+                }
+            }
+        });
+    }
+
+    // Check the pipelines before recording the commands,
+    // so the render pass can assume that the pipelines exist.
+    // Only the changed entities are checked, since the pipelines are not changed after the creation.
+    fn validate_states(&self) -> Result<(), RendererError> {
         let rendered_entity = &self.dynamic_renderer.rendered_entity;
+        if !rendered_entity.dirty {
+            return Ok(());
+        }
+        let mut has_mesh = rendered_entity.meta_list.iter().map(Option::is_some);
+        match find_missing_state(
+            &rendered_entity.entities,
+            &mut has_mesh,
+            &self.render_pipelines,
+        ) {
+            Some(state) => Err(RendererError::StateNotFound(state.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn render_actual(&mut self, view: TextureView) -> Result<(), RendererError> {
+        self.validate_states()?;
+        self.update_scene();
+        self.prepare_entities();
 
         let mut encoder = self
             .dynamic_renderer
@@ -918,110 +1694,125 @@ impl Renderer {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
         if self.scene.shadow_uniform.use_shadow {
+            // The options of the lights may be changed after the renderer is created.
+            self.shadow_baker
+                .prepare_pipelines(&self.dynamic_renderer.device, &self.scene);
+
             // shadow pass
             encoder.push_debug_group("shadow pass");
+            let shadow_baker = &self.shadow_baker;
+            for (i, (light, layers)) in self
+                .scene
+                .scene
+                .lights
+                .iter()
+                .zip(&self.scene.shadow_uniform.layers)
+                .enumerate()
             {
-                for (i, light) in self.scene.scene.lights.iter().enumerate() {
-                    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: None,
-                        color_attachments: &[],
-                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                            view: &self.shadow_baker.views[i],
-                            depth_ops: Some(wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(1.0),
-                                store: true,
-                            }),
-                            stencil_ops: None,
-                        }),
-                    });
-
-                    // Set shadow projection dynamically
-                    {
-                        let size = mem::size_of::<[f32; 16]>() as wgpu::BufferAddress;
-                        let uniform_alignment = {
-                            let alignment = self
-                                .dynamic_renderer
-                                .device
-                                .limits()
-                                .min_uniform_buffer_offset_alignment
-                                as wgpu::BufferAddress;
-                            align_to(size, alignment)
-                        };
-                        let offset = i as wgpu::BufferAddress * uniform_alignment;
-
-                        let default = Default::default();
-                        let shadow = if let Some(shadow) = light.shadow() {
-                            shadow
-                        } else {
-                            &default
-                        };
-                        self.shadow_baker.camera.update(
-                            &self.dynamic_renderer.queue,
-                            shadow.transform(light),
-                            offset,
-                        );
-
-                        rpass.set_bind_group(
-                            0,
-                            &self.shadow_baker.camera.bind_group,
-                            &[offset as u32],
-                        );
-                    }
-
-                    let mut i = 0;
-                    traverse_entities_with_transform(
-                        &rendered_entity.entities,
-                        &Transform::default(),
-                        &mut |entity, transform| {
-                            rpass.set_pipeline(
-                                self.shadow_baker
-                                    .render_pipelines
-                                    .get(&entity.state)
-                                    .expect("Specified renderer state is not found"),
-                            );
-
-                            let meta = rendered_entity
-                                .meta_list
-                                .get(i)
-                                .expect("The length of meta_list must match with entities");
-
-                            i += 1;
-
-                            if let Some(meta) = meta {
-                                self.prepare_shadow_entity(meta, transform);
-                                rpass.set_bind_group(
-                                    1,
-                                    &self.shadow_baker.entity.entity_bind_group,
-                                    &[meta.uniform_offset as u32],
-                                );
-
-                                rpass.set_vertex_buffer(0, meta.vertex_buf.slice(..));
-                                match &meta.index_buf {
-                                    Some(index_buf) => {
-                                        rpass.set_index_buffer(
-                                            index_buf.slice(..),
-                                            wgpu::IndexFormat::Uint16,
-                                        );
-                                        rpass.draw_indexed(0..meta.index_length, 0, 0..1);
-                                    }
-                                    None => rpass.draw(0..meta.vertex_length, 0..1),
-                                }
-                            }
-                        },
+                let key = match light.shadow() {
+                    Some(style) => ShadowPipelineKey::new(style, layers),
+                    None => continue,
+                };
+                // The pipelines are prepared above, so the light is skipped just in case.
+                let render_pipelines = match shadow_baker.render_pipelines.get(&key) {
+                    Some(render_pipelines) => render_pipelines,
+                    None => continue,
+                };
+                // Each cascade of the light is baked into its own layer.
+                for (layer, light_transform) in self.scene.light_uniform.shadow_layers(i) {
+                    let target = ShadowTarget {
+                        view: &shadow_baker.views[layer as usize],
+                        variance_view: key
+                            .variance()
+                            .then(|| &shadow_baker.variance_views[layer as usize]),
+                        map_size: Some(layers.map_size),
+                        render_pipelines,
+                    };
+                    self.bake_shadow(&mut encoder, target, layer, light_transform);
+                }
+                // The point light is baked into the faces of the cube map.
+                for (layer, light_transform) in self.scene.light_uniform.shadow_cube_layers(i) {
+                    let target = ShadowTarget {
+                        view: &shadow_baker.cube_views[layer as usize],
+                        variance_view: None,
+                        map_size: None,
+                        render_pipelines,
+                    };
+                    self.bake_shadow(
+                        &mut encoder,
+                        target,
+                        self.scene.shadow_uniform.layer_len + layer,
+                        light_transform,
                     );
                 }
             }
             encoder.pop_debug_group();
         }
 
+        // render target pass
+        // Render targets are drawn before the main view to use them as textures in the same frame.
+        encoder.push_debug_group("render target pass");
+        for (target, style) in self
+            .dynamic_renderer
+            .rendered_targets
+            .iter()
+            .zip(&self.scene.scene.render_targets)
+        {
+            let (view, resolve_target, store) = match &target.msaa_view {
+                Some(msaa_view) => (msaa_view, Some(&target.view), false),
+                None => (&target.view, None, true),
+            };
+            let background = rgba_to_array_64(style.background());
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: background[0],
+                            g: background[1],
+                            b: background[2],
+                            a: background[3],
+                        }),
+                        store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &target.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            self.draw_scene(
+                &mut rpass,
+                &target.scene_uniform.bind_group,
+                style.camera(),
+                self.dynamic_renderer
+                    .rendered_texture
+                    .bind_group_without(target.texture_idx),
+                true,
+            );
+        }
+        encoder.pop_debug_group();
+
         let msaa_samples = self
             .scene
             .config
             .max_samples
             .min(self.scene.scene.msaa_samples);
 
-        let (view, resolve_target, store) = if msaa_samples <= 1 {
-            (view, None, true)
+        // The scene is rendered into the HDR target if the post-processing is enabled.
+        let target = match &self.post_processor {
+            Some(post_processor) => post_processor.input_view(),
+            None => &view,
+        };
+        let multi_sampled_view;
+        let (color_view, resolve_target, store) = if msaa_samples <= 1 {
+            (target, None, true)
         } else {
             let texture = self
                 .dynamic_renderer
@@ -1035,13 +1826,13 @@ impl Renderer {
                     mip_level_count: 1,
                     sample_count: msaa_samples,
                     dimension: wgpu::TextureDimension::D2,
-                    format: self.config.format,
+                    format: self.scene.config.color_format,
                     usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
                     label: None,
                     view_formats: &[],
                 });
-            let multi_sampled_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-            (multi_sampled_view, Some(&view), false)
+            multi_sampled_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (&multi_sampled_view, Some(target), false)
         };
 
         // forward pass
@@ -1050,7 +1841,7 @@ impl Renderer {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: color_view,
                     resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -1073,65 +1864,73 @@ impl Renderer {
                     }
                 }),
             });
-            rpass.set_bind_group(0, &self.scene.scene_uniform.bind_group, &[]);
-            rpass.set_bind_group(2, &self.scene.light_uniform.bind_group, &[]);
-            rpass.set_bind_group(3, &self.scene.shadow_uniform.bind_group, &[]);
-
-            let mut i = 0;
-            traverse_entities_with_transform(
-                &rendered_entity.entities,
-                &Transform::default(),
-                &mut |entity, transform| {
-                    rpass.set_pipeline(
-                        self.render_pipelines
-                            .get(&entity.state)
-                            .expect("Specified renderer state is not found"),
-                    );
-                    let meta = rendered_entity
-                        .meta_list
-                        .get(i)
-                        .expect("The length of meta_list must match with entities");
-
-                    i += 1;
-
-                    if let Some(meta) = meta {
-                        self.prepare_entity(entity, meta, transform);
 
-                        rpass.set_bind_group(
-                            1,
-                            &rendered_entity.entity_bind_group,
-                            &[meta.uniform_offset as u32],
-                        );
-
-                        if let Some(bind_group) =
-                            &self.dynamic_renderer.rendered_texture.texture_bind_group
-                        {
-                            rpass.set_bind_group(4, bind_group, &[]);
-                        }
+            let texture_bind_group = self
+                .dynamic_renderer
+                .rendered_texture
+                .texture_bind_group
+                .as_ref();
+            let (width, height) = (self.config.width, self.config.height);
+            if self.set_viewport(&mut rpass, &self.scene.scene.viewport, width, height) {
+                self.draw_scene(
+                    &mut rpass,
+                    &self.scene.scene_uniform.bind_group,
+                    &self.scene.scene.camera,
+                    texture_bind_group,
+                    true,
+                );
+            }
 
-                        rpass.set_vertex_buffer(0, meta.vertex_buf.slice(..));
-                        match &meta.index_buf {
-                            Some(index_buf) => {
-                                rpass.set_index_buffer(
-                                    index_buf.slice(..),
-                                    wgpu::IndexFormat::Uint16,
-                                );
-                                rpass.draw_indexed(0..meta.index_length, 0, 0..1);
-                            }
-                            None => rpass.draw(0..meta.vertex_length, 0..1),
-                        }
-                    }
-                },
-            );
+            // Views are drawn over the main camera in ascending order.
+            let mut views: Vec<_> = self
+                .scene
+                .scene
+                .views
+                .iter()
+                .zip(&self.scene.view_uniforms)
+                .collect();
+            views.sort_by_key(|(view, _)| *view.order());
+            for (view, uniform) in views {
+                rpass.push_debug_group(view.id());
+                if self.set_viewport(&mut rpass, view.viewport(), width, height) {
+                    self.clear_pipelines.clear(&mut rpass, view.clear());
+                    self.draw_scene(
+                        &mut rpass,
+                        &uniform.bind_group,
+                        view.camera(),
+                        texture_bind_group,
+                        matches!(view.clear(), ClearPolicy::Color(_)),
+                    );
+                }
+                rpass.pop_debug_group();
+            }
         }
         encoder.pop_debug_group();
 
+        if let Some(post_processor) = &self.post_processor {
+            post_processor.run(&mut encoder, &view);
+        }
+
         self.dynamic_renderer.queue.submit(Some(encoder.finish()));
+
+        Ok(())
     }
 
     pub fn render(&mut self) {
+        self.try_render().expect("Failed to render");
+    }
+
+    pub fn try_render(&mut self) -> Result<(), RendererError> {
         let (view, frame) = if let Some(surface) = &self.surface {
-            let frame = surface.get_current_texture().unwrap();
+            let frame = match surface.get_current_texture() {
+                Ok(frame) => frame,
+                // Reconfigure the surface and retry once, e.g. when the window is resized or minimized.
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    surface.configure(&self.dynamic_renderer.device, &self.config);
+                    surface.get_current_texture()?
+                }
+                Err(e) => return Err(e.into()),
+            };
             (
                 frame
                     .texture
@@ -1169,21 +1968,28 @@ impl Renderer {
             )
         };
 
-        self.render_actual(view);
+        self.render_actual(view)?;
 
         if let Some(frame) = frame {
             frame.present();
         }
+
+        Ok(())
     }
 
     pub fn load_as_image(&mut self) -> Vec<u8> {
+        self.try_load_as_image()
+            .expect("Failed to load the rendered image")
+    }
+
+    pub fn try_load_as_image(&mut self) -> Result<Vec<u8>, RendererError> {
         if self.surface.is_some() {
-            panic!("You already have a window as render target view.");
+            return Err(RendererError::SurfaceTarget);
         }
 
         let dst_texture = match &self.dst_texture {
             Some(txt) => txt,
-            None => return vec![],
+            None => return Ok(vec![]),
         };
 
         // Need to handle bytes per row due to wgpu restriction
@@ -1228,22 +2034,321 @@ impl Renderer {
         self.dynamic_renderer.queue.submit(Some(cmd_buf.finish()));
 
         let dst_buffer_slice = dst_buffer.slice(..);
-        dst_buffer_slice.map_async(wgpu::MapMode::Read, |_| ());
+        let (sender, receiver) = mpsc::channel();
+        dst_buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            // The receiver is alive until the result is received.
+            let _ = sender.send(result);
+        });
         self.dynamic_renderer.device.poll(wgpu::Maintain::Wait);
+        // The mapping is treated as failed if the callback is dropped without being called.
+        receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
         let buf = dst_buffer_slice.get_mapped_range().to_vec();
 
         let mut result = vec![];
         for chunk in buf.chunks(padded_bytes_per_row as usize) {
-            result
-                .write_all(&chunk[..(unpadded_bytes_per_row as usize)])
-                .unwrap();
+            result.extend_from_slice(&chunk[..(unpadded_bytes_per_row as usize)]);
+        }
+        Ok(result)
+    }
+
+    // Limit the drawing to the viewport. Returns `false` if the viewport is empty.
+    fn set_viewport(
+        &self,
+        rpass: &mut wgpu::RenderPass,
+        viewport: &Viewport,
+        width: u32,
+        height: u32,
+    ) -> bool {
+        let (x, y, w, h) = viewport.to_pixels(width, height);
+        if w == 0 || h == 0 {
+            return false;
+        }
+        rpass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0., 1.);
+        rpass.set_scissor_rect(x, y, w, h);
+        true
+    }
+
+    // Draw the entities from the camera into the render pass.
+    // While a render target is drawn, `texture_bind_group` must be the one from
+    // `RenderedTexture::bind_group_without`. The entities which use the texture of the target
+    // are still drawn, but they sample the empty placeholder instead.
+    // The skybox should be drawn only if the color of the viewport is cleared,
+    // otherwise it covers the previous views.
+    fn draw_scene<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        scene_bind_group: &'a BindGroup,
+        camera: &CameraStyle,
+        texture_bind_group: Option<&'a BindGroup>,
+        draw_skybox: bool,
+    ) {
+        let rendered_entity = &self.dynamic_renderer.rendered_entity;
+
+        rpass.set_bind_group(0, scene_bind_group, &[]);
+        rpass.set_bind_group(2, &self.scene.light_uniform.bind_group, &[]);
+        rpass.set_bind_group(3, &self.scene.shadow_uniform.bind_group, &[]);
+
+        // Opaque entities are drawn first, and transparent entities are collected to sort.
+        let eye = camera.calc_position_vec3().as_glam();
+        let frustum = Frustum::from_mat4(&camera.transform());
+        let mut transparent_entities = vec![];
+        let mut i = 0;
+        traverse_entities(&rendered_entity.entities, &mut |entity| {
+            let meta = rendered_entity.meta_list.get(i);
+
+            i += 1;
+
+            if let Some(Some(meta)) = meta {
+                let transform = &entity.world_transform;
+                if !meta.is_visible(&frustum, transform) {
+                    return;
+                }
+
+                if entity.state.blend == BlendMode::Opaque {
+                    self.draw_entity(rpass, entity, meta, texture_bind_group);
+                } else {
+                    let distance = transform.translation.as_glam().distance(eye);
+                    transparent_entities.push((distance, (entity, meta)));
+                }
+            }
+        });
+
+        // The skybox fills the background behind the opaque entities,
+        // and the transparent entities are blended over it.
+        // Without the cubemap, the background is drawn only to blend the fog.
+        let scene = &self.scene.scene;
+        if draw_skybox && (scene.skybox.is_some() || scene.fog.is_some()) {
+            self.skybox.draw(rpass, scene_bind_group);
+        }
+
+        // Transparent pass
+        rpass.push_debug_group("transparent pass");
+        sort_back_to_front(&mut transparent_entities);
+        for (_, (entity, meta)) in transparent_entities {
+            self.draw_entity(rpass, entity, meta, texture_bind_group);
+        }
+        rpass.pop_debug_group();
+    }
+
+    fn draw_entity<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        entity: &Entity,
+        meta: &'a RenderedEntityMeta,
+        texture_bind_group: Option<&'a BindGroup>,
+    ) {
+        let rendered_entity = &self.dynamic_renderer.rendered_entity;
+
+        // The states are validated before rendering, so the entity is skipped just in case.
+        let pipeline = match self.render_pipelines.get(&entity.state) {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+        rpass.set_pipeline(pipeline);
+
+        rpass.set_bind_group(
+            1,
+            &rendered_entity.entity_bind_group,
+            &[meta.uniform_offset as u32],
+        );
+
+        if let Some(bind_group) = texture_bind_group {
+            rpass.set_bind_group(4, bind_group, &[]);
+        }
+
+        rpass.set_vertex_buffer(0, meta.vertex_buf.slice(..));
+        rpass.set_vertex_buffer(
+            1,
+            meta.instance_buf
+                .as_ref()
+                .unwrap_or(&rendered_entity.default_instance_buf)
+                .slice(..),
+        );
+        match &meta.index_buf {
+            Some(index_buf) => {
+                rpass.set_index_buffer(index_buf.slice(..), meta.index_format);
+                rpass.draw_indexed(0..meta.index_length, 0, 0..meta.instance_length);
+            }
+            None => rpass.draw(0..meta.vertex_length, 0..meta.instance_length),
+        }
+    }
+
+    // Upload the instances and the uniforms of the changed entities.
+    // The children of the changed entity are also uploaded since their transform depends on the parent.
+    // The world transforms of the entities are updated here, so this must be called before drawing.
+    fn prepare_entities(&mut self) {
+        if !self.dynamic_renderer.rendered_entity.dirty {
+            return;
+        }
+        let DynamicRenderer {
+            rendered_entity,
+            rendered_texture,
+            device,
+            queue,
+            ..
+        } = &mut self.dynamic_renderer;
+        let RenderedEntity {
+            entities,
+            meta_list,
+            entity_uniform_buf,
+            dirty: tree_dirty,
+            ..
+        } = rendered_entity;
+        *tree_dirty = false;
+        let shadow_uniform_buf = self
+            .scene
+            .shadow_uniform
+            .use_shadow
+            .then_some(&self.shadow_baker.entity.entity_uniform_buf);
+
+        let mut metas = meta_list.iter_mut();
+        let mut uploaded_textures = false;
+        let mut replaced_textures = vec![];
+        traverse_dirty_entities(
+            entities,
+            &Transform::default(),
+            false,
+            &mut |entity, dirty| {
+                let meta = match metas.next() {
+                    Some(Some(meta)) => meta,
+                    _ => return,
+                };
+                if entity.textures_dirty {
+                    entity.textures_dirty = false;
+                    replaced_textures.extend(entity.texture_indices.values());
+                    uploaded_textures |=
+                        Self::prepare_textures(entity, meta, rendered_texture, device, queue);
+                }
+                if !dirty && !meta.needs_upload {
+                    return;
+                }
+                meta.needs_upload = false;
+
+                let transform = &entity.world_transform;
+                Self::prepare_instances(entity, meta, device, queue);
+                Self::prepare_entity(entity, meta, transform, entity_uniform_buf, queue);
+                if let Some(buf) = shadow_uniform_buf {
+                    Self::prepare_shadow_entity(meta, transform, buf, queue);
+                }
+            },
+        );
+
+        // The textures of the replaced materials are freed if no entity uses them.
+        let released = !replaced_textures.is_empty()
+            && self
+                .dynamic_renderer
+                .release_unused_textures(replaced_textures);
+        if uploaded_textures || released {
+            self.dynamic_renderer
+                .rendered_texture
+                .update_bind_group(&self.dynamic_renderer.device);
         }
-        result
     }
 
-    // FIXME(@keiya01): Dirty check
-    fn prepare_entity(&self, entity: &Entity, meta: &RenderedEntityMeta, transform: &Transform) {
-        let renderer_entity = &self.dynamic_renderer.rendered_entity;
+    // Resolve the textures of the material against the texture array again,
+    // since the material may be replaced after the entity is pushed.
+    // The images which are not uploaded yet are uploaded. Returns true if any image is uploaded.
+    fn prepare_textures(
+        entity: &mut Entity,
+        meta: &mut RenderedEntityMeta,
+        rendered_texture: &mut RenderedTexture,
+        device: &Device,
+        queue: &Queue,
+    ) -> bool {
+        let mut uploaded = false;
+        entity.texture_indices = entity
+            .material
+            .textures
+            .iter()
+            .map(|(slot, image)| {
+                let idx = rendered_texture.find(image).unwrap_or_else(|| {
+                    uploaded = true;
+                    rendered_texture.push(image, device, queue)
+                });
+                (*slot, idx)
+            })
+            .collect();
+
+        // The tangent space is needed only for the normal map.
+        let use_tangent_space = entity.texture_idx(TextureSlot::Normal).is_some();
+        if meta.use_tangent_space != use_tangent_space {
+            meta.use_tangent_space = use_tangent_space;
+            RenderedEntity::update_entity(meta, device, queue);
+        }
+        uploaded
+    }
+
+    fn prepare_instances(
+        entity: &Entity,
+        meta: &mut RenderedEntityMeta,
+        device: &Device,
+        queue: &Queue,
+    ) {
+        if entity.instances.is_empty() {
+            meta.instance_buf = None;
+            meta.instance_length = 1;
+            meta.instances.clear();
+            meta.instance_bounding = None;
+            return;
+        }
+        let instances: Vec<_> = entity
+            .instances
+            .iter()
+            .map(InstanceBuffer::from_instance)
+            .collect();
+        let changed = |i: usize| match meta.instances.get(i) {
+            Some(prev) => bytemuck::bytes_of(prev) != bytemuck::bytes_of(&instances[i]),
+            None => true,
+        };
+        match &mut meta.instance_buf {
+            // Only the runs of the changed instances are uploaded.
+            Some(instance_buf) if instances.len() == meta.instances.len() => {
+                let stride = mem::size_of::<InstanceBuffer>();
+                let mut i = 0;
+                let mut uploaded = false;
+                while i < instances.len() {
+                    if !changed(i) {
+                        i += 1;
+                        continue;
+                    }
+                    let start = i;
+                    while i < instances.len() && changed(i) {
+                        i += 1;
+                    }
+                    queue.write_buffer(
+                        instance_buf,
+                        (start * stride) as BufferAddress,
+                        bytemuck::cast_slice(&instances[start..i]),
+                    );
+                    uploaded = true;
+                }
+                if !uploaded {
+                    return;
+                }
+            }
+            Some(instance_buf) => RenderedEntity::write_or_grow_buffer(
+                instance_buf,
+                bytemuck::cast_slice(&instances),
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                "Instance Buffer",
+                device,
+                queue,
+            ),
+            None => meta.instance_buf = RenderedEntity::make_instance_buffer(&instances, device),
+        }
+        meta.instance_length = instances.len() as u32;
+        meta.instances = instances;
+        meta.update_instance_bounding();
+    }
+
+    fn prepare_entity(
+        entity: &Entity,
+        meta: &RenderedEntityMeta,
+        transform: &Transform,
+        entity_uniform_buf: &Buffer,
+        queue: &Queue,
+    ) {
+        let material = &entity.material;
         let transform = transform.as_mat4();
         let normal_transform = Mat3::from_mat4(transform)
             .inverse()
@@ -1251,10 +2356,21 @@ impl Renderer {
             .to_cols_array_2d();
         let buf = EntityUniformBuffer {
             transform: transform.to_cols_array_2d(),
-            color: rgba_to_array(&entity.fill_color),
-            reflection: Reflection::from_style(&entity.reflection),
-            tex_idx: [entity.tex_idx.unwrap_or(-1), 0, 0, 0],
-            normal_idx: [entity.normal_map_idx.unwrap_or(-1), 0, 0, 0],
+            color: rgba_to_array(&material.base_color),
+            reflection: Reflection::from_style(&material.reflection),
+            pbr: Pbr::from_style(&material.shading, &material.pbr),
+            tex_idx: [
+                entity.texture_idx(TextureSlot::BaseColor).unwrap_or(-1),
+                0,
+                0,
+                0,
+            ],
+            normal_idx: [
+                entity.texture_idx(TextureSlot::Normal).unwrap_or(-1),
+                0,
+                0,
+                0,
+            ],
             #[rustfmt::skip]
             normal_transform: [
                 [normal_transform[0][0], normal_transform[0][1], normal_transform[0][2], 0.],
@@ -1262,34 +2378,150 @@ impl Renderer {
                 [normal_transform[2][0], normal_transform[2][1], normal_transform[2][2], 0.],
                 [0., 0., 0., 0.],
             ],
-            receive_shadow: [entity.receive_shadow as u32, 0, 0, 0],
+            receive_shadow: [material.receive_shadow as u32, 0, 0, 0],
+            double_sided: [
+                matches!(entity.state.cull_mode, CullMode::None) as u32,
+                0,
+                0,
+                0,
+            ],
         };
 
-        self.dynamic_renderer.queue.write_buffer(
-            &renderer_entity.entity_uniform_buf,
+        queue.write_buffer(
+            entity_uniform_buf,
             meta.uniform_offset,
             bytemuck::bytes_of(&buf),
         );
     }
 
-    // FIXME(@keiya01): Dirty check
-    fn prepare_shadow_entity(&self, meta: &RenderedEntityMeta, transform: &Transform) {
-        let renderer_entity = &self.shadow_baker.entity;
+    fn prepare_shadow_entity(
+        meta: &RenderedEntityMeta,
+        transform: &Transform,
+        shadow_uniform_buf: &Buffer,
+        queue: &Queue,
+    ) {
         let transform = transform.as_mat4();
         let buf = ShadowEntityUniformBuffer {
             transform: transform.to_cols_array_2d(),
         };
-        self.dynamic_renderer.queue.write_buffer(
-            &renderer_entity.entity_uniform_buf,
+        queue.write_buffer(
+            shadow_uniform_buf,
             meta.uniform_offset,
             bytemuck::bytes_of(&buf),
         );
     }
 }
 
-fn traverse_entities_with_transform<F>(entities: &[Entity], transform: &Transform, f: &mut F)
+fn remove_entity_recursive(
+    entities: &mut Vec<Entity>,
+    id: &str,
+    pos: &mut usize,
+) -> Option<Entity> {
+    for i in 0..entities.len() {
+        if entities[i].id == id {
+            return Some(entities.remove(i));
+        }
+        *pos += 1;
+        if let Some(entity) = remove_entity_recursive(&mut entities[i].children, id, pos) {
+            return Some(entity);
+        }
+    }
+    None
+}
+
+// Sort the entities by the distance from the camera in descending order,
+// so they are drawn from back to front to blend with the color behind them.
+fn sort_back_to_front<T>(entities: &mut [(f32, T)]) {
+    entities.sort_by(|a, b| b.0.total_cmp(&a.0));
+}
+
+// Pack the offsets from the start in order, and mark the moved slots to be uploaded again.
+fn compact_offsets<'a>(slots: impl Iterator<Item = (&'a mut u64, &'a mut bool)>, alignment: u64) {
+    for (i, (offset, needs_upload)) in slots.enumerate() {
+        let packed = i as u64 * alignment;
+        if *offset != packed {
+            *offset = packed;
+            *needs_upload = true;
+        }
+    }
+}
+
+fn find_entity_position(entities: &[Entity], id: &str, pos: &mut usize) -> bool {
+    for entity in entities {
+        if entity.id == id {
+            return true;
+        }
+        *pos += 1;
+        if find_entity_position(&entity.children, id, pos) {
+            return true;
+        }
+    }
+    false
+}
+
+fn collect_texture_indices(entities: &[Entity], indices: &mut Vec<i32>) {
+    for entity in entities {
+        indices.extend(entity.texture_indices.values());
+        collect_texture_indices(&entity.children, indices);
+    }
+}
+
+// Find the state of the changed entity which has a mesh but no pipeline.
+// The entities without a mesh are never drawn, so their states don't need the pipelines.
+// `has_mesh` is ordered as same as `traverse_entities_with_transform`.
+fn find_missing_state<'a, P>(
+    entities: &'a [Entity],
+    has_mesh: &mut impl Iterator<Item = bool>,
+    pipelines: &HashMap<EntityRendererState, P>,
+) -> Option<&'a EntityRendererState> {
+    for entity in entities {
+        let drawn = has_mesh.next().unwrap_or(false);
+        if entity.dirty && drawn && !pipelines.contains_key(&entity.state) {
+            return Some(&entity.state);
+        }
+        if let Some(state) = find_missing_state(&entity.children, has_mesh, pipelines) {
+            return Some(state);
+        }
+    }
+    None
+}
+
+// Traverse the entities and clear the dirty flags.
+// `dirty` passed to `f` is true if the entity or its ancestor is changed.
+// The world transform is composed only for the changed entities, and cached for the others.
+fn traverse_dirty_entities<F>(
+    entities: &mut [Entity],
+    transform: &Transform,
+    parent_dirty: bool,
+    f: &mut F,
+) where
+    F: FnMut(&mut Entity, bool),
+{
+    for entity in entities.iter_mut() {
+        let dirty = parent_dirty || entity.dirty;
+        if dirty {
+            entity.world_transform = transform.mul(&entity.transform);
+        }
+        entity.dirty = false;
+        f(entity, dirty);
+        traverse_dirty_entities(&mut entity.children, &entity.world_transform, dirty, f)
+    }
+}
+
+// Traverse the entities with the world transforms cached by `traverse_dirty_entities`.
+fn traverse_entities<'a, F>(entities: &'a [Entity], f: &mut F)
+where
+    F: FnMut(&'a Entity),
+{
+    for entity in entities.iter() {
+        f(entity);
+        traverse_entities(&entity.children, f)
+    }
+}
+
+fn traverse_entities_with_transform<'a, F>(entities: &'a [Entity], transform: &Transform, f: &mut F)
 where
-    F: FnMut(&Entity, &Transform),
+    F: FnMut(&'a Entity, &Transform),
 {
     for entity in entities.iter() {
         let transform = transform.mul(&entity.transform);
@@ -1297,3 +2529,141 @@ where
         traverse_entities_with_transform(&entity.children, &transform, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_missing_state() {
+        let line = EntityRendererState {
+            topology: Topology::LineList,
+            ..Default::default()
+        };
+        let pipelines = HashMap::from([(EntityRendererState::default(), ())]);
+        // A group entity without a mesh doesn't need the pipeline of its state.
+        let group = || Entity {
+            id: "group".to_string(),
+            state: line.clone(),
+            children: vec![Entity {
+                dirty: true,
+                ..Default::default()
+            }],
+            dirty: true,
+            ..Default::default()
+        };
+
+        let entities = [group()];
+        let mut has_mesh = [false, true].into_iter();
+        assert_eq!(
+            find_missing_state(&entities, &mut has_mesh, &pipelines),
+            None
+        );
+
+        let entities = [group()];
+        let mut has_mesh = [true, true].into_iter();
+        assert_eq!(
+            find_missing_state(&entities, &mut has_mesh, &pipelines),
+            Some(&line)
+        );
+
+        // The entity which is not changed is already validated.
+        let mut entities = [group()];
+        entities[0].dirty = false;
+        let mut has_mesh = [true, true].into_iter();
+        assert_eq!(
+            find_missing_state(&entities, &mut has_mesh, &pipelines),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compact_offsets() {
+        let entity = |id: &str, children| Entity {
+            id: id.to_string(),
+            children,
+            ..Default::default()
+        };
+        let mut entities = vec![
+            entity("a", vec![]),
+            entity("b", vec![entity("c", vec![]), entity("d", vec![])]),
+            entity("e", vec![]),
+            entity("f", vec![]),
+        ];
+        // `c` doesn't have a mesh.
+        let mut slots = vec![
+            Some((0, false)),
+            Some((256, false)),
+            None,
+            Some((512, false)),
+            Some((768, false)),
+            Some((1024, false)),
+        ];
+
+        // Remove the subtree in the middle.
+        let mut start = 0;
+        let removed = remove_entity_recursive(&mut entities, "b", &mut start).unwrap();
+        assert_eq!(removed.id, "b");
+        assert_eq!(start, 1);
+        slots.drain(start..start + 3);
+
+        compact_offsets(slots.iter_mut().flatten().map(|(o, u)| (o, u)), 256);
+        assert_eq!(
+            slots,
+            vec![Some((0, false)), Some((256, true)), Some((512, true))]
+        );
+    }
+
+    #[test]
+    fn test_sort_back_to_front() {
+        let mut entities = vec![(1., "near"), (10., "far"), (5., "middle"), (0., "eye")];
+        sort_back_to_front(&mut entities);
+        assert_eq!(
+            entities.iter().map(|(_, id)| *id).collect::<Vec<_>>(),
+            ["far", "middle", "near", "eye"]
+        );
+    }
+
+    #[test]
+    fn test_traverse_dirty_entities() {
+        let translation = |x, y, z| {
+            Transform::from_translation_rotation_scale_array(
+                [x, y, z],
+                [0., 0., 0., 1.],
+                [1., 1., 1.],
+            )
+        };
+        let mut entities = vec![Entity {
+            transform: translation(1., 0., 0.),
+            children: vec![Entity {
+                transform: translation(0., 2., 0.),
+                dirty: true,
+                ..Default::default()
+            }],
+            dirty: true,
+            ..Default::default()
+        }];
+        let traverse = |entities: &mut [Entity]| {
+            let mut dirty = vec![];
+            traverse_dirty_entities(entities, &Transform::default(), false, &mut |_, d| {
+                dirty.push(d)
+            });
+            dirty
+        };
+        let world = |entity: &Entity| entity.world_transform.translation.as_glam().to_array();
+
+        assert_eq!(traverse(&mut entities), [true, true]);
+        assert_eq!(world(&entities[0].children[0]), [1., 2., 0.]);
+
+        // The cached world transforms are kept for the clean entities.
+        assert_eq!(traverse(&mut entities), [false, false]);
+        assert_eq!(world(&entities[0].children[0]), [1., 2., 0.]);
+
+        // The dirty parent composes the world transform of the clean child again.
+        entities[0].set_transform(translation(5., 0., 0.));
+        assert!(!entities[0].children[0].dirty);
+        assert_eq!(traverse(&mut entities), [true, true]);
+        assert_eq!(world(&entities[0]), [5., 0., 0.]);
+        assert_eq!(world(&entities[0].children[0]), [5., 2., 0.]);
+    }
+}
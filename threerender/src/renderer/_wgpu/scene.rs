@@ -1,24 +1,27 @@
 use std::{f32::consts, mem};
 
 use bytemuck::{Pod, Zeroable};
-use glam::{Affine3A, Mat4};
+use glam::{Affine3A, Mat4, Vec3};
 use threerender_math::trs::Translation;
-use threerender_traits::entity::ReflectionStyle;
+use threerender_traits::material::{PBRStyle, ReflectionStyle, ShadingModel};
 use wgpu::{
     util::DeviceExt, Adapter, BindGroup, BindGroupLayout, Buffer, BufferAddress, Device, Queue,
     Sampler, Texture, TextureView,
 };
 
-use crate::{HemisphereLightStyle, LightModel, LightStyle, Scene as AbstractedScene, ShadowStyle};
+use crate::{
+    CameraStyle, CascadeStyle, FogMode, FogStyle, HeightFalloff, HemisphereLightStyle, LightModel,
+    LightStyle, Scene as AbstractedScene, ShadowStyle, ShadowType,
+};
 
-use super::unit::rgb_to_array;
+use super::{post_process::PostProcessor, unit::rgb_to_array};
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Reflection {
     intensity: f32,
     specular: f32,
-    _padding: [f32; 6],
+    _padding: [f32; 2],
 }
 
 impl Reflection {
@@ -27,7 +30,32 @@ impl Reflection {
         Self {
             intensity: reflection.intensity,
             specular: reflection.specular,
-            _padding: [0.; 6],
+            _padding: [0.; 2],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Pbr {
+    metalness: f32,
+    roughness: f32,
+    shading: u32,
+    _padding: f32,
+}
+
+impl Pbr {
+    pub(super) fn from_style(shading: &ShadingModel, style: &PBRStyle) -> Self {
+        Self {
+            metalness: style.metalness.clamp(0., 1.),
+            roughness: style.roughness.clamp(0., 1.),
+            shading: match shading {
+                ShadingModel::Unlit => 0,
+                ShadingModel::Lambert => 1,
+                ShadingModel::Phong => 2,
+                ShadingModel::PBR => 3,
+            },
+            _padding: 0.,
         }
     }
 }
@@ -60,15 +88,37 @@ pub struct Light {
     brightness: f32,
     model: u32,
 
-    _padding: [f32; 3],
+    // For point and spot light
+    range: f32,
+    decay: f32,
+
+    _padding: f32,
+
+    // For spot light
+    direction: [f32; 3],
+    inner_cone: f32,
+    outer_cone: f32,
+
+    _padding2: [f32; 3],
 
     hemisphere: HemisphereLight,
     shadow: Shadow,
 }
 
 impl Light {
-    fn from_light_style(style: &LightStyle) -> Self {
+    fn from_light_style(
+        style: &LightStyle,
+        camera: &CameraStyle,
+        layers: ShadowLayers,
+        texture_size: (u32, u32),
+    ) -> Self {
         let color = rgb_to_array(style.base().color());
+        let (range, decay) = match (style.point(), style.spot()) {
+            (Some(p), _) => (p.range, p.decay),
+            (_, Some(s)) => (s.range, s.decay),
+            _ => (0., 0.),
+        };
+        let spot = style.spot().clone().unwrap_or_default();
         Self {
             color: [color[0], color[1], color[2], 1.],
             position: Affine3A::from_rotation_translation(
@@ -83,12 +133,30 @@ impl Light {
                 LightModel::Directional => 1,
                 LightModel::Hemisphere => 2,
                 LightModel::Ambient => 3,
+                LightModel::Point => 4,
+                LightModel::Spot => 5,
             },
 
-            _padding: [0., 0., 0.],
+            range,
+            decay,
+
+            _padding: 0.,
+
+            direction: style
+                .base()
+                .rotation
+                .as_glam()
+                .mul_vec3(spot.direction.as_glam())
+                .normalize_or_zero()
+                .to_array(),
+            // Pass cosine to compare with the dot product in the shader.
+            inner_cone: (spot.inner_angle * consts::PI / 180.).cos(),
+            outer_cone: (spot.outer_angle * consts::PI / 180.).cos(),
+
+            _padding2: [0., 0., 0.],
 
             hemisphere: HemisphereLight::from_style(style.hemisphere()),
-            shadow: Shadow::from_shadow_style(style),
+            shadow: Shadow::from_shadow_style(style, camera, layers, texture_size),
         }
     }
 }
@@ -96,6 +164,9 @@ impl Light {
 pub(super) struct LightUniform {
     pub(super) buf: Buffer,
     data: Vec<Light>,
+    // The generations of the styles which `data` is computed from.
+    generations: Vec<Option<u64>>,
+    camera_generation: Option<u64>,
     pub(super) bind_group_layout: BindGroupLayout,
     pub(super) bind_group: BindGroup,
 }
@@ -103,14 +174,12 @@ pub(super) struct LightUniform {
 impl LightUniform {
     fn new(device: &Device, lights: Vec<Light>) -> Self {
         // Create light style uniforms
-        let light_uniform_size = (lights.len() * mem::size_of::<Light>()) as wgpu::BufferAddress;
-        let light_storage_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        let light_storage_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Light Uniform"),
-            size: light_uniform_size,
+            contents: bytemuck::cast_slice(&lights),
             usage: wgpu::BufferUsages::UNIFORM
                 | wgpu::BufferUsages::COPY_SRC
                 | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
         });
 
         let light_bind_group_layout =
@@ -139,32 +208,94 @@ impl LightUniform {
 
         Self {
             buf: light_storage_buf,
+            generations: vec![None; lights.len()],
+            camera_generation: None,
             data: lights,
             bind_group_layout: light_bind_group_layout,
             bind_group: light_bind_group,
         }
     }
 
-    pub(super) fn update(&self, queue: &Queue) {
-        for (i, light) in self.data.iter().enumerate() {
-            queue.write_buffer(
-                &self.buf,
-                (i * mem::size_of::<Light>()) as BufferAddress,
-                bytemuck::bytes_of(light),
-            );
-        }
+    // Recreate the buffer to fit the lights. The bind group layout is kept for the pipelines.
+    fn resize(&mut self, device: &Device, lights: Vec<Light>) {
+        self.buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Uniform"),
+            contents: bytemuck::cast_slice(&lights),
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        self.bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.buf.as_entire_binding(),
+            }],
+            label: None,
+        });
+        self.generations = vec![None; lights.len()];
+        self.data = lights;
     }
 
-    pub(super) fn len(&self) -> usize {
-        self.data.len()
+    fn write(&self, queue: &Queue, idx: usize) {
+        queue.write_buffer(
+            &self.buf,
+            (idx * mem::size_of::<Light>()) as BufferAddress,
+            bytemuck::bytes_of(&self.data[idx]),
+        );
+    }
+
+    // Returns the layers of the shadow map and their projections to bake the shadow of the light.
+    pub(super) fn shadow_layers(&self, idx: usize) -> impl Iterator<Item = (u32, Mat4)> + '_ {
+        let shadow = &self.data[idx].shadow;
+        shadow.projections[..shadow.cascade_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, projection)| {
+                (
+                    shadow.layer + i as u32,
+                    Mat4::from_cols_array_2d(projection),
+                )
+            })
+    }
+
+    // Returns the layers of the cube map and their projections to bake the shadow of the point light.
+    pub(super) fn shadow_cube_layers(&self, idx: usize) -> impl Iterator<Item = (u32, Mat4)> {
+        let light = &self.data[idx];
+        let shadow = &light.shadow;
+        let faces = match shadow.cube {
+            -1 => 0..0,
+            cube => cube as u32 * 6..(cube as u32 + 1) * 6,
+        };
+        faces.zip(cube_transforms(
+            Vec3::from(light.position),
+            shadow.near_plane,
+            shadow.far_plane,
+        ))
     }
 }
 
+// The projections of the faces ordered as `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`.
+fn cube_transforms(position: Vec3, near: f32, far: f32) -> [Mat4; 6] {
+    let projection = Mat4::perspective_rh(consts::FRAC_PI_2, 1., near, far);
+    // The faces of the cube map are flipped vertically against the render target.
+    let projection = Mat4::from_scale(Vec3::new(1., -1., 1.)) * projection;
+    [
+        (Vec3::X, Vec3::NEG_Y),
+        (Vec3::NEG_X, Vec3::NEG_Y),
+        (Vec3::Y, Vec3::Z),
+        (Vec3::NEG_Y, Vec3::NEG_Z),
+        (Vec3::Z, Vec3::NEG_Y),
+        (Vec3::NEG_Z, Vec3::NEG_Y),
+    ]
+    .map(|(direction, up)| projection * Mat4::look_at_rh(position, position + direction, up))
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Shadow {
-    // The alpha chanel is always ignored. This is to align buffer for wgsl.
-    projection: [[f32; 4]; 4],
+    // Only the first projection is used without the cascades.
+    projections: [[[f32; 4]; 4]; CascadeStyle::MAX_COUNT as usize],
     use_shadow: u32,
     opacity: f32,
 
@@ -173,39 +304,198 @@ pub struct Shadow {
     light_uv: f32,
     near_plane: f32,
 
-    _padding: [f32; 3],
+    // The first layer of the shadow map
+    layer: u32,
+    // 0 if the light has no shadow.
+    cascade_count: u32,
+    cascade_blend: f32,
+
+    // For point light
+    far_plane: f32,
+    // The index of the cube map, or -1.
+    cube: i32,
+
+    // The area of the layer used by the light in UV.
+    map_scale: [f32; 2],
+    normal_offset: f32,
+    pcf_kernel_size: u32,
+
+    _padding: [f32; 2],
 }
 
 impl Shadow {
-    fn from_shadow_style(light: &LightStyle) -> Self {
-        let shadow = light.shadow().as_ref();
+    fn from_shadow_style(
+        light: &LightStyle,
+        camera: &CameraStyle,
+        layers: ShadowLayers,
+        texture_size: (u32, u32),
+    ) -> Self {
+        // The light which has no layers is drawn without the shadow.
+        let shadow = light
+            .shadow()
+            .as_ref()
+            .filter(|_| layers.len > 0 || layers.cube.is_some());
+        let (width, height) = layers.map_size;
+        // The cascades are snapped to the texels of the area used by the light.
+        let transforms = shadow.map_or_else(Vec::new, |s| {
+            s.transforms(light, camera, layers.len, width.min(height))
+        });
+        let mut projections = [Mat4::ZERO.to_cols_array_2d(); CascadeStyle::MAX_COUNT as usize];
+        for (projection, transform) in projections.iter_mut().zip(&transforms) {
+            *projection = transform.to_cols_array_2d();
+        }
         Self {
-            projection: shadow
-                .map_or_else(|| Mat4::ZERO, |s| s.transform(light))
-                .to_cols_array_2d(),
+            projections,
             use_shadow: shadow.is_some() as u32,
             opacity: shadow.map(|s| s.opacity).unwrap_or(1.),
-            shadow_type: shadow.map_or(0, |s| s.shadow_type.as_u32()),
+            shadow_type: shadow.map_or(0, |s| match s.shadow_type {
+                // Fall back to the normal shadow if the moments are not allocated.
+                ShadowType::Variance if !layers.variance => ShadowType::Normal.as_u32(),
+                _ => s.shadow_type.as_u32(),
+            }),
             light_uv: shadow.map_or(0., |s| s.fov * consts::PI / 180.),
             near_plane: shadow.map_or(0., |s| s.near),
-            _padding: [0., 0., 0.],
+            layer: layers.base,
+            cascade_count: transforms.len() as u32,
+            cascade_blend: shadow
+                .and_then(|s| s.cascade.as_ref())
+                .map_or(0., |c| c.blend),
+            far_plane: shadow.map_or(0., |s| s.far),
+            cube: layers.cube.map_or(-1, |cube| cube as i32),
+            map_scale: match layers.cube {
+                Some(_) => [1., 1.],
+                None => [
+                    width as f32 / texture_size.0 as f32,
+                    height as f32 / texture_size.1 as f32,
+                ],
+            },
+            normal_offset: shadow.map_or(0., |s| s.normal_offset),
+            pcf_kernel_size: shadow.map_or(1, |s| s.pcf_kernel_size.max(1)),
+            _padding: [0., 0.],
         }
     }
 }
 
+// The layers of the shadow map which are assigned to the light.
+#[derive(Clone, Copy)]
+pub(super) struct ShadowLayers {
+    base: u32,
+    len: u32,
+    // The point light uses the cube map instead of the layers.
+    pub(super) cube: Option<u32>,
+    // The light is drawn into the top-left area of this size.
+    pub(super) map_size: (u32, u32),
+    // Whether the moments of the variance shadow map are baked for the light.
+    pub(super) variance: bool,
+}
+
+impl ShadowLayers {
+    // For the light added after the shadow map is created.
+    const NONE: Self = Self {
+        base: 0,
+        len: 0,
+        cube: None,
+        map_size: (1, 1),
+        variance: false,
+    };
+}
+
 pub(super) struct ShadowUniform {
     pub(super) bind_group_layout: BindGroupLayout,
     pub(super) bind_group: BindGroup,
     pub(super) texture: Texture,
+    // The moments of the variance shadow map which have the same layers as `texture`.
+    pub(super) variance_texture: Texture,
     pub(super) use_shadow: bool,
+    // Ordered as same as the lights.
+    pub(super) layers: Vec<ShadowLayers>,
+    pub(super) layer_len: u32,
+    pub(super) cube_texture: Texture,
+    // The number of the layers in the cube map, which has 6 layers per cube.
+    pub(super) cube_layer_len: u32,
+    // Whether the cube map is bound as an array, otherwise it has only one cube.
+    pub(super) cube_array: bool,
+    // The size of the layers, which fits the largest map of the lights.
+    texture_size: (u32, u32),
 }
 
 impl ShadowUniform {
     const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
-    const DEFAULT_MAX_LIGHT_LENGTH: u32 = 10;
+    pub(super) const VARIANCE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 
-    fn new(device: &Device, use_shadow: bool, map_size: (u32, u32)) -> Self {
-        let (sampler, sampler_comparison, texture, view) = Self::create_texture(device, map_size);
+    fn new(device: &Device, lights: &[LightStyle], map_size: (u32, u32), cube_array: bool) -> Self {
+        let use_shadow = lights.iter().any(|light| light.shadow().is_some());
+        let use_variance = lights.iter().any(|light| {
+            !matches!(light.model(), LightModel::Point)
+                && matches!(
+                    light.shadow(),
+                    Some(ShadowStyle {
+                        shadow_type: ShadowType::Variance,
+                        ..
+                    })
+                )
+        });
+        // The resolution is capped by the device, since all layers are allocated at the largest one.
+        let max_size = device.limits().max_texture_dimension_2d;
+        let cap = |(w, h): (u32, u32)| (w.clamp(1, max_size), h.clamp(1, max_size));
+        let map_size = cap(map_size);
+        // Each cascade of the light uses its own layer.
+        // The lights without the shadow use no layer.
+        let mut layer_len = 0;
+        let mut cube_len = 0;
+        let layers: Vec<ShadowLayers> = lights
+            .iter()
+            .map(|light| {
+                let len = light.shadow().as_ref().map_or(0, |s| s.layer_len(light));
+                // Without the cube array textures, only the first point light casts the shadow.
+                let cube = match (light.model(), light.shadow()) {
+                    (LightModel::Point, Some(_)) if cube_array || cube_len == 0 => {
+                        cube_len += 1;
+                        Some(cube_len - 1)
+                    }
+                    _ => None,
+                };
+                let layers = ShadowLayers {
+                    base: layer_len,
+                    len,
+                    cube,
+                    map_size: light
+                        .shadow()
+                        .as_ref()
+                        .and_then(|s| s.map_size)
+                        .map_or(map_size, cap),
+                    variance: use_variance,
+                };
+                layer_len += len;
+                layers
+            })
+            .collect();
+        // Keep at least one layer to bind the texture even if there is no shadow.
+        let layer_len = layer_len.max(1);
+        // The layers are shared with all lights, so they fit the largest map.
+        let texture_size = layers
+            .iter()
+            .filter(|l| l.cube.is_none())
+            .fold(map_size, |(w, h), l| {
+                (w.max(l.map_size.0), h.max(l.map_size.1))
+            });
+        let (sampler, sampler_comparison, texture, view) =
+            Self::create_texture(device, texture_size, layer_len);
+        // The moments are allocated only if they are used, since they are large.
+        let (variance_texture, variance_view) = match use_variance {
+            true => Self::create_variance_texture(device, texture_size, layer_len),
+            false => Self::create_variance_texture(device, (1, 1), 1),
+        };
+        // Keep at least one cube to bind the texture even if there is no point light.
+        let cube_layer_len = cube_len.max(1) * 6;
+        let cube_size = layers
+            .iter()
+            .filter(|l| l.cube.is_some())
+            .map(|l| l.map_size.0.min(l.map_size.1))
+            .max()
+            .unwrap_or(map_size.0.min(map_size.1));
+        let (cube_texture, cube_view) =
+            Self::create_cube_texture(device, cube_size, cube_layer_len, cube_array);
 
         let shadow_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -233,6 +523,26 @@ impl ShadowUniform {
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: Self::cube_view_dimension(cube_array),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -251,6 +561,14 @@ impl ShadowUniform {
                     binding: 2,
                     resource: wgpu::BindingResource::Sampler(&sampler_comparison),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&cube_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&variance_view),
+                },
             ],
             label: None,
         });
@@ -259,13 +577,21 @@ impl ShadowUniform {
             bind_group_layout: shadow_bind_group_layout,
             bind_group: shadow_bind_group,
             texture,
+            variance_texture,
             use_shadow,
+            layers,
+            layer_len,
+            cube_texture,
+            cube_layer_len,
+            cube_array,
+            texture_size,
         }
     }
 
     fn create_texture(
         device: &Device,
         map_size: (u32, u32),
+        layer_len: u32,
     ) -> (Sampler, Sampler, Texture, TextureView) {
         // Create other resources
         let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -295,7 +621,7 @@ impl ShadowUniform {
             size: wgpu::Extent3d {
                 width: map_size.0,
                 height: map_size.1,
-                depth_or_array_layers: Self::DEFAULT_MAX_LIGHT_LENGTH,
+                depth_or_array_layers: layer_len,
             },
             mip_level_count: 1,
             sample_count: 1,
@@ -305,7 +631,11 @@ impl ShadowUniform {
             label: None,
             view_formats: &[],
         });
-        let shadow_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // The view is always an array to bind it even if the texture has only one layer.
+        let shadow_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
 
         (
             shadow_sampler,
@@ -314,6 +644,108 @@ impl ShadowUniform {
             shadow_view,
         )
     }
+
+    // The view is always an array to bind it even if the texture has only one layer.
+    fn create_variance_texture(
+        device: &Device,
+        size: (u32, u32),
+        layer_len: u32,
+    ) -> (Texture, TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: layer_len,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::VARIANCE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: Some("shadow variance"),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        (texture, view)
+    }
+
+    // The faces of the cube map must be square.
+    fn create_cube_texture(
+        device: &Device,
+        size: u32,
+        layer_len: u32,
+        cube_array: bool,
+    ) -> (Texture, TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: layer_len,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: Some("shadow cube"),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(Self::cube_view_dimension(cube_array)),
+            ..Default::default()
+        });
+        (texture, view)
+    }
+
+    // The cube array textures are not supported on some backends, e.g. WebGL2.
+    fn cube_view_dimension(cube_array: bool) -> wgpu::TextureViewDimension {
+        match cube_array {
+            true => wgpu::TextureViewDimension::CubeArray,
+            false => wgpu::TextureViewDimension::Cube,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Fog {
+    color: [f32; 3],
+    // 0 disables the fog.
+    mode: u32,
+    start: f32,
+    end: f32,
+    density: f32,
+    // 0 disables the height falloff.
+    height_falloff: f32,
+    base_height: f32,
+    _padding: [f32; 3],
+}
+
+impl Fog {
+    fn from_style(style: &Option<FogStyle>) -> Self {
+        let fog = match style {
+            Some(fog) => fog,
+            None => return Self::zeroed(),
+        };
+        let (start, end) = match fog.mode() {
+            FogMode::Linear { start, end } => (*start, *end),
+            _ => (0., 0.),
+        };
+        let height = fog.height_falloff().unwrap_or(HeightFalloff::new(0., 0.));
+        Self {
+            color: rgb_to_array(fog.color()),
+            mode: fog.mode().as_u32(),
+            start,
+            end,
+            density: *fog.density(),
+            height_falloff: height.falloff.max(0.),
+            base_height: height.base_height,
+            _padding: [0., 0., 0.],
+        }
+    }
 }
 
 #[repr(C)]
@@ -322,14 +754,25 @@ pub(super) struct SceneData {
     pub(super) model: [f32; 16],
     pub(super) eye: [f32; 3],
     pub(super) num_lights: u32,
+    // To unproject the clip space, e.g. the view direction of the skybox.
+    pub(super) inv_model: [f32; 16],
+    pub(super) fog: Fog,
 }
 
 impl SceneData {
     pub(super) fn from_style(style: &AbstractedScene) -> Self {
+        Self::from_camera(&style.camera, style)
+    }
+
+    // The lights and the fog are shared by all cameras of the scene.
+    pub(super) fn from_camera(camera: &CameraStyle, scene: &AbstractedScene) -> Self {
+        let model = camera.transform();
         Self {
-            model: style.camera.transform().to_cols_array(),
-            num_lights: style.lights.len() as u32,
-            eye: style.camera.calc_position_vec3().as_glam().to_array(),
+            model: model.to_cols_array(),
+            inv_model: model.inverse().to_cols_array(),
+            num_lights: scene.lights.len() as u32,
+            eye: camera.calc_position_vec3().as_glam().to_array(),
+            fog: Fog::from_style(&scene.fog),
         }
     }
 }
@@ -339,6 +782,8 @@ pub(super) struct SceneUniform {
     pub(super) bind_group_layout: BindGroupLayout,
     pub(super) bind_group: BindGroup,
     pub(super) data: SceneData,
+    // The generations of the camera and the fog which `data` is computed from.
+    generation: Option<(u64, u64)>,
 }
 
 impl SceneUniform {
@@ -377,20 +822,48 @@ impl SceneUniform {
             bind_group_layout: scene_bind_group_layout,
             bind_group: scene_bind_group,
             data,
+            generation: None,
         }
     }
 
     pub(super) fn update(&self, queue: &Queue) {
         queue.write_buffer(&self.buf, 0, bytemuck::bytes_of(&self.data));
     }
+
+    // The data is recomputed only when the camera or the fog may be changed,
+    // and it is uploaded only when the data is actually changed.
+    pub(super) fn update_camera(
+        &mut self,
+        queue: &Queue,
+        camera: &CameraStyle,
+        camera_generation: u64,
+        scene: &AbstractedScene,
+    ) {
+        let generation = Some((camera_generation, scene.fog_generation));
+        if self.generation == generation {
+            return;
+        }
+        self.generation = generation;
+        let data = SceneData::from_camera(camera, scene);
+        if bytemuck::bytes_of(&data) == bytemuck::bytes_of(&self.data) {
+            return;
+        }
+        self.data = data;
+        self.update(queue);
+    }
 }
 
 pub(super) struct SceneConfig {
+    // The format which the entities are rendered into.
+    pub(super) color_format: wgpu::TextureFormat,
     pub(super) max_samples: u32,
+    pub(super) float32_filterable: bool,
 }
 
 pub struct Scene {
     pub(super) scene_uniform: SceneUniform,
+    // Ordered as same as `Scene::views`.
+    pub(super) view_uniforms: Vec<SceneUniform>,
     pub(super) light_uniform: LightUniform,
     pub(super) shadow_uniform: ShadowUniform,
     pub(super) forward_depth: Option<TextureView>,
@@ -406,58 +879,123 @@ impl Scene {
         config: &wgpu::SurfaceConfiguration,
     ) -> Self {
         let scene_uniform = SceneUniform::new(device, SceneData::from_style(&scene));
-        let mut has_shadow = false;
-        let light_data = scene
-            .lights
+        let view_uniforms = scene
+            .views
             .iter()
-            .map(|light| {
-                has_shadow = if has_shadow {
-                    has_shadow
-                } else {
-                    light.shadow().is_some()
-                };
-                Light::from_light_style(light)
-            })
+            .map(|view| SceneUniform::new(device, SceneData::from_camera(view.camera(), &scene)))
             .collect();
-        let light_uniform = LightUniform::new(device, light_data);
         let shadow_uniform = ShadowUniform::new(
             device,
-            has_shadow,
+            &scene.lights,
             scene
                 .shadow_options
                 .as_ref()
                 .map_or_else(|| ShadowStyle::DEFAULT_MAP_SIZE, |s| *s.map_size()),
+            adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::CUBE_ARRAY_TEXTURES),
         );
-        let sample_flags = adapter.get_texture_format_features(config.format).flags;
+        let light_data = scene
+            .lights
+            .iter()
+            .zip(&shadow_uniform.layers)
+            .map(|(light, layers)| {
+                Light::from_light_style(light, &scene.camera, *layers, shadow_uniform.texture_size)
+            })
+            .collect();
+        let light_uniform = LightUniform::new(device, light_data);
+        let color_format = match scene.post_process {
+            Some(_) => PostProcessor::HDR_FORMAT,
+            None => config.format,
+        };
+        let sample_flags = adapter.get_texture_format_features(color_format).flags;
 
         Scene {
             scene_uniform,
+            view_uniforms,
             light_uniform,
             shadow_uniform,
             forward_depth: None,
             scene,
             config: SceneConfig {
+                color_format,
                 max_samples: max_samples(&sample_flags),
+                float32_filterable: device
+                    .features()
+                    .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+                    && is_float32_filterable(adapter),
             },
         }
     }
 
     pub(super) fn update_scene(&mut self, queue: &Queue) {
-        self.scene_uniform.data = SceneData::from_style(&self.scene);
-        self.scene_uniform.update(queue);
+        let scene = &self.scene;
+        self.scene_uniform
+            .update_camera(queue, &scene.camera, scene.camera_generation, scene);
+        for (uniform, view) in self.view_uniforms.iter_mut().zip(&scene.views) {
+            uniform.update_camera(queue, view.camera(), view.generation, scene);
+        }
     }
 
-    pub(super) fn update_light(&mut self, queue: &Queue) {
-        self.light_uniform.data = self
-            .scene
-            .lights
-            .iter()
-            .map(Light::from_light_style)
-            .collect();
-        self.light_uniform.update(queue);
+    // Only the changed lights are recomputed and uploaded.
+    // The buffer is recreated if the lights are added after the creation.
+    // The added lights are drawn without the shadow, since the shadow map is not allocated for them.
+    pub(super) fn update_light(&mut self, device: &Device, queue: &Queue) {
+        let light_uniform = &mut self.light_uniform;
+        let shadow_uniform = &self.shadow_uniform;
+        let scene = &self.scene;
+        let light = |i: usize, style: &LightStyle| {
+            let layers = shadow_uniform
+                .layers
+                .get(i)
+                .copied()
+                .unwrap_or(ShadowLayers::NONE);
+            Light::from_light_style(style, &scene.camera, layers, shadow_uniform.texture_size)
+        };
+        if scene.lights.len() > light_uniform.data.len() {
+            light_uniform.resize(
+                device,
+                scene
+                    .lights
+                    .iter()
+                    .enumerate()
+                    .map(|(i, style)| light(i, style))
+                    .collect(),
+            );
+        }
+
+        let camera_changed = light_uniform.camera_generation != Some(scene.camera_generation);
+        light_uniform.camera_generation = Some(scene.camera_generation);
+        for (i, style) in scene.lights.iter().enumerate() {
+            // The cascades follow the camera.
+            let follows_camera = camera_changed
+                && style
+                    .shadow()
+                    .as_ref()
+                    .is_some_and(|shadow| shadow.follows_camera(style));
+            if light_uniform.generations[i] == Some(style.generation) && !follows_camera {
+                continue;
+            }
+            light_uniform.generations[i] = Some(style.generation);
+            let light = light(i, style);
+            let prev = &mut light_uniform.data[i];
+            if bytemuck::bytes_of(prev) != bytemuck::bytes_of(&light) {
+                *prev = light;
+                light_uniform.write(queue, i);
+            }
+        }
     }
 }
 
+// 32 bit float textures are filterable only with the adapter specific format features.
+pub(super) fn is_float32_filterable(adapter: &Adapter) -> bool {
+    adapter
+        .get_texture_format_features(wgpu::TextureFormat::Rgba32Float)
+        .flags
+        .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE)
+}
+
 fn max_samples(sample_flags: &wgpu::TextureFormatFeatureFlags) -> u32 {
     if sample_flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X16) {
         16
@@ -471,3 +1009,86 @@ fn max_samples(sample_flags: &wgpu::TextureFormatFeatureFlags) -> u32 {
         1
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use threerender_color::rgb::RGB;
+
+    #[test]
+    fn test_cube_transforms() {
+        let position = Vec3::new(1., 2., 3.);
+        let transforms = cube_transforms(position, 0.1, 100.);
+        // The face and the texture coordinates looked up by the direction in the cube map.
+        let lookup = |d: Vec3| {
+            let a = d.abs();
+            let (face, sc, tc, ma) = if a.x >= a.y && a.x >= a.z {
+                match d.x > 0. {
+                    true => (0, -d.z, -d.y, a.x),
+                    false => (1, d.z, -d.y, a.x),
+                }
+            } else if a.y >= a.z {
+                match d.y > 0. {
+                    true => (2, d.x, d.z, a.y),
+                    false => (3, d.x, -d.z, a.y),
+                }
+            } else {
+                match d.z > 0. {
+                    true => (4, d.x, -d.y, a.z),
+                    false => (5, -d.x, -d.y, a.z),
+                }
+            };
+            (face, (sc / ma + 1.) / 2., (tc / ma + 1.) / 2.)
+        };
+        for d in [
+            Vec3::new(3., 1., -2.),
+            Vec3::new(-3., -1., 2.),
+            Vec3::new(1., 3., 2.),
+            Vec3::new(-1., -3., 2.),
+            Vec3::new(-1., 2., 3.),
+            Vec3::new(2., -1., -3.),
+        ] {
+            let (face, u, v) = lookup(d);
+            let p = transforms[face].project_point3(position + d);
+            // The top-left corner of the render target is `(0, 0)`.
+            assert!((p.x * 0.5 + 0.5 - u).abs() < 1e-5, "{d}");
+            assert!((0.5 - p.y * 0.5 - v).abs() < 1e-5, "{d}");
+            // Same as the depth calculated in the shader.
+            let distance = d.abs().max_element();
+            let depth = 100. * (distance - 0.1) / ((100. - 0.1) * distance);
+            assert!((p.z - depth).abs() < 1e-5, "{d}");
+        }
+    }
+
+    #[test]
+    fn test_fog_from_style() {
+        // The fog is disabled without the style.
+        let fog = Fog::from_style(&None);
+        assert_eq!(fog.mode, 0);
+        assert_eq!(fog.density, 0.);
+
+        let style = FogStyle::new(
+            FogMode::Linear {
+                start: 10.,
+                end: 100.,
+            },
+            RGB::new(255, 0, 0),
+            0.8,
+        );
+        let fog = Fog::from_style(&Some(style));
+        assert_eq!(fog.mode, 1);
+        assert_eq!((fog.start, fog.end), (10., 100.));
+        assert_eq!(fog.color, [1., 0., 0.]);
+        assert_eq!(fog.density, 0.8);
+        // The height falloff is disabled by default.
+        assert_eq!(fog.height_falloff, 0.);
+
+        // The range is used only by the linear fog, and the negative falloff is clamped.
+        let style = FogStyle::new(FogMode::ExponentialSquared, RGB::new(0, 0, 0), 0.05)
+            .with_height_falloff(HeightFalloff::new(2., -1.));
+        let fog = Fog::from_style(&Some(style));
+        assert_eq!(fog.mode, 3);
+        assert_eq!((fog.start, fog.end), (0., 0.));
+        assert_eq!((fog.height_falloff, fog.base_height), (0., 2.));
+    }
+}
@@ -0,0 +1,37 @@
+struct Fog {
+  color: vec3<f32>,
+  // 0: off
+  // 1: linear
+  // 2: exponential
+  // 3: exponential squared
+  mode: u32,
+  // For linear fog
+  start: f32,
+  end: f32,
+  density: f32,
+  // 0 disables the height falloff.
+  height_falloff: f32,
+  base_height: f32,
+}
+
+// Returns the amount of the fog in `0..1` at the distance from the camera and the world height.
+fn calc_fog(fog: Fog, distance: f32, height: f32) -> f32 {
+  var amount = 0.0;
+  switch fog.mode {
+    case 1u: {
+      amount = clamp((distance - fog.start) / max(fog.end - fog.start, 0.0001), 0.0, 1.0) * fog.density;
+    }
+    case 2u: {
+      amount = 1.0 - exp(-fog.density * distance);
+    }
+    case 3u: {
+      let d = fog.density * distance;
+      amount = 1.0 - exp(-d * d);
+    }
+    default: {}
+  }
+  if fog.height_falloff > 0.0 {
+    amount *= exp(-fog.height_falloff * max(height - fog.base_height, 0.0));
+  }
+  return clamp(amount, 0.0, 1.0);
+}
@@ -5,3 +5,5 @@
 
 #include builtin::light::hemisphere
 #include builtin::light::directional
+#include builtin::light::point
+#include builtin::light::spot
@@ -0,0 +1,22 @@
+fn calc_distance_attenuation(distance: f32, ulight: UniformLight) -> f32 {
+    var attenuation = 1.0 / max(pow(max(distance, 0.0001), ulight.decay), 0.01);
+    // Smoothly cut off the light at the range.
+    if ulight.range > 0.0 {
+        attenuation *= pow(clamp(1.0 - pow(distance / ulight.range, 4.0), 0.0, 1.0), 2.0);
+    }
+    return attenuation;
+}
+
+fn calc_point_light(world_normal: vec3<f32>, light_normal: vec3<f32>, distance: f32, ulight: UniformLight) -> PointLight {
+    let attenuation = calc_distance_attenuation(distance, ulight);
+    let blightness = vec4<f32>(ulight.brightness * attenuation);
+
+    let diffuse = max(dot(world_normal, light_normal), 0.0) * ulight.color * blightness;
+
+    var p: PointLight;
+    p.color = diffuse;
+    p.normal = light_normal;
+    p.attenuation = attenuation;
+
+    return p;
+}
@@ -0,0 +1,31 @@
+// The cube map is looked up by the direction from the point light.
+// The depth is compared in the face which the direction hits.
+fn point_shadow(
+    light_position: vec3<f32>,
+    position: vec4<f32>,
+    info: UniformShadow,
+#ifdef CUBE_ARRAY
+    t_shadow_cube: texture_depth_cube_array,
+#else
+    t_shadow_cube: texture_depth_cube,
+#end
+    sampler_shadow_comparison: sampler_comparison,
+) -> f32 {
+    let direction = position.xyz / position.w - light_position;
+    let abs_direction = abs(direction);
+    // The distance along the axis of the face
+    let distance = max(abs_direction.x, max(abs_direction.y, abs_direction.z));
+    if distance >= info.far_plane {
+        return 1.0;
+    }
+
+    // Same as the depth of the perspective projection in the face.
+    let near = info.near_plane;
+    let far = info.far_plane;
+    let depth = far * (distance - near) / ((far - near) * distance);
+#ifdef CUBE_ARRAY
+    return textureSampleCompareLevel(t_shadow_cube, sampler_shadow_comparison, direction, info.cube, depth);
+#else
+    return textureSampleCompareLevel(t_shadow_cube, sampler_shadow_comparison, direction, depth);
+#end
+}
@@ -0,0 +1,16 @@
+// The exponents of the exponential variance shadow map.
+// They are small enough not to overflow the 16 bit float.
+const EVSM_POSITIVE_EXPONENT = 5.0;
+const EVSM_NEGATIVE_EXPONENT = 5.0;
+
+// Warp the depth in 0..1 into the positive and negative exponential depth.
+fn evsm_warp(depth: f32) -> vec2<f32> {
+    let d = depth * 2.0 - 1.0;
+    return vec2(exp(EVSM_POSITIVE_EXPONENT * d), -exp(-EVSM_NEGATIVE_EXPONENT * d));
+}
+
+// The first and second moments of the warped depth.
+fn evsm_moments(depth: f32) -> vec4<f32> {
+    let warped = evsm_warp(depth);
+    return vec4(warped.x, warped.x * warped.x, warped.y, warped.y * warped.y);
+}
@@ -2,38 +2,108 @@
 #include builtin::light::shadow::utils
 #include builtin::light::shadow::normal
 #include builtin::light::shadow::pcss
+#include builtin::light::shadow::evsm
+#include builtin::light::shadow::variance
+#include builtin::light::shadow::cube
 
-fn fetch_shadow(light_id: u32, homogeneous_coords: vec4<f32>, info: UniformShadow, t_shadow: texture_depth_2d_array, sampler_shadow: sampler, sampler_shadow_comparison: sampler_comparison) -> f32 {
+fn fetch_shadow(layer: u32, homogeneous_coords: vec4<f32>, info: UniformShadow, t_shadow: texture_depth_2d_array, t_shadow_variance: texture_2d_array<f32>, sampler_shadow: sampler, sampler_shadow_comparison: sampler_comparison) -> f32 {
     if (homogeneous_coords.w <= 0.0) {
         return 1.0;
     }
 
     var visible: f32;
     if (info.shadow_type == 1u) {
-      visible = percent_closer_soft_shadow(light_id, homogeneous_coords, info, t_shadow, sampler_shadow, sampler_shadow_comparison);    
+      visible = percent_closer_soft_shadow(layer, homogeneous_coords, info, t_shadow, sampler_shadow, sampler_shadow_comparison);    
+    } else if (info.shadow_type == 2u) {
+      visible = variance_shadow(layer, homogeneous_coords, info, t_shadow_variance, sampler_shadow);
     } else {
-      visible = normal_shadow(light_id, homogeneous_coords, t_shadow, sampler_shadow, sampler_shadow_comparison);    
+      visible = normal_shadow(layer, homogeneous_coords, info, t_shadow, sampler_shadow, sampler_shadow_comparison);    
     }
     return visible;
 }
 
+// Move the position along the normal to avoid the acne on the slope.
+fn normal_offset_position(position: vec4<f32>, normal: vec3<f32>, info: UniformShadow) -> vec4<f32> {
+    return vec4(position.xyz + normal * info.normal_offset * position.w, position.w);
+}
+
+// Returns how far the coordinates are inside the shadow map in UV. Negative if outside.
+fn cascade_edge(homogeneous_coords: vec4<f32>) -> f32 {
+    let coords = shadow_coords(homogeneous_coords);
+    if coords.z > 1.0 {
+        return -1.0;
+    }
+    return min(min(coords.x, coords.y), min(1.0 - coords.x, 1.0 - coords.y));
+}
+
 fn calc_shadow_mask(
-  idx: u32,
-  shadow_position: vec4<f32>,
+  local_position: vec4<f32>,
+  normal: vec3<f32>,
   info: UniformShadow,
   t_shadow: texture_depth_2d_array,
+  t_shadow_variance: texture_2d_array<f32>,
   sampler_shadow: sampler,
   sampler_comparison_shadow: sampler_comparison,
 ) -> f32 {
+    let position = normal_offset_position(local_position, normal, info);
+    // Indexing the array needs the variable.
+    var projections = info.projections;
+    let last = max(info.cascade_count, 1u) - 1u;
+    // Select the finest cascade which covers the position.
+    // The last cascade is always used as same as the shadow without the cascades.
+    var idx = 0u;
+    loop {
+        if idx >= last || cascade_edge(projections[idx] * position) >= 0.0 {
+            break;
+        }
+        idx += 1u;
+    }
+
     // project into the light space
+    let shadow_position = projections[idx] * position;
     let shadow = fetch_shadow(
-      idx,
+      info.layer + idx,
       shadow_position,
       info,
       t_shadow,
+      t_shadow_variance,
       sampler_shadow,
       sampler_comparison_shadow,
     );
+
+    // Blend into the next cascade around the border to hide the seam.
+    let edge = cascade_edge(shadow_position);
+    if idx < last && edge < info.cascade_blend {
+        let next = fetch_shadow(
+          info.layer + idx + 1u,
+          projections[idx + 1u] * position,
+          info,
+          t_shadow,
+          t_shadow_variance,
+          sampler_shadow,
+          sampler_comparison_shadow,
+        );
+        return mix(next, shadow, edge / info.cascade_blend);
+    }
     // add light contribution
     return shadow;
 }
+
+fn calc_point_shadow_mask(
+  light_position: vec3<f32>,
+  local_position: vec4<f32>,
+  normal: vec3<f32>,
+  info: UniformShadow,
+#ifdef CUBE_ARRAY
+  t_shadow_cube: texture_depth_cube_array,
+#else
+  t_shadow_cube: texture_depth_cube,
+#end
+  sampler_comparison_shadow: sampler_comparison,
+) -> f32 {
+    if info.cube == -1 {
+        return 1.0;
+    }
+    let position = normal_offset_position(local_position, normal, info);
+    return point_shadow(light_position, position, info, t_shadow_cube, sampler_comparison_shadow);
+}
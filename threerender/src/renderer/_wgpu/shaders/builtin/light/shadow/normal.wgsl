@@ -1,5 +1,11 @@
-fn normal_shadow(light_id: u32, homogeneous_coords: vec4<f32>, t_shadow: texture_depth_2d_array, sampler_shadow: sampler, sampler_shadow_comparison: sampler_comparison) -> f32 {
+fn normal_shadow(layer: u32, homogeneous_coords: vec4<f32>, info: UniformShadow, t_shadow: texture_depth_2d_array, sampler_shadow: sampler, sampler_shadow_comparison: sampler_comparison) -> f32 {
     let coords = shadow_coords(homogeneous_coords);
-    let shadow = textureSampleCompareLevel(t_shadow, sampler_shadow_comparison, coords.xy, i32(light_id), coords.z);
-    return shadow;
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_shadow));
+    let size = max(info.pcf_kernel_size, 1u);
+    var sum = 0.0;
+    for (var i = 0u; i < size * size; i += 1u) {
+        let uv = shadow_uv(coords.xy + pcf_offset(i, info, texel), info);
+        sum += textureSampleCompareLevel(t_shadow, sampler_shadow_comparison, uv, i32(layer), coords.z);
+    }
+    return sum / f32(size * size);
 }
@@ -13,7 +13,7 @@ fn find_blocker(
     num_blockers: ptr<function, f32>,
     uv: vec2<f32>,
     z_receiver: f32,
-    light_id: u32,
+    layer: u32,
     info: UniformShadow,
     t_shadow: texture_depth_2d_array,
     sampler_shadow: sampler,
@@ -28,8 +28,8 @@ fn find_blocker(
         let shadow_map_depth = textureSampleLevel(
             t_shadow,
             sampler_shadow,
-            uv + (*poissonDisk)[i] * searchWidth,
-            i32(light_id),
+            shadow_uv(uv + (*poissonDisk)[i] * searchWidth, info),
+            i32(layer),
             0.,
         );
         if (shadow_map_depth < z_receiver) {
@@ -44,7 +44,8 @@ fn pcf_filter(
     uv: vec2<f32>,
     z_receiver: f32,
     filter_radius_uv: f32,
-    light_id: u32,
+    layer: u32,
+    info: UniformShadow,
     t_shadow: texture_depth_2d_array,
     sampler_shadow_comparison: sampler_comparison,
     poissonDisk: ptr<function, array<vec2<f32>, 16>>,
@@ -55,8 +56,8 @@ fn pcf_filter(
         sum += textureSampleCompareLevel(
             t_shadow,
             sampler_shadow_comparison,
-            uv + offset,
-            i32(light_id),
+            shadow_uv(uv + offset, info),
+            i32(layer),
             z_receiver
         );
     }
@@ -65,7 +66,7 @@ fn pcf_filter(
 
 // Ref: https://developer.download.nvidia.com/whitepapers/2008/PCSS_Integration.pdf
 fn percent_closer_soft_shadow(
-    light_id: u32,
+    layer: u32,
     homogeneous_coords: vec4<f32>,
     info: UniformShadow,
     t_shadow: texture_depth_2d_array,
@@ -99,7 +100,7 @@ fn percent_closer_soft_shadow(
     // STEP 1: blocker search
     var avg_blocker_depth: f32 = 0.;
     var num_blockers: f32 = 0.;
-    find_blocker(&avg_blocker_depth, &num_blockers, uv, z_receiver, light_id, info, t_shadow, sampler_shadow, &poissonDisk);
+    find_blocker(&avg_blocker_depth, &num_blockers, uv, z_receiver, layer, info, t_shadow, sampler_shadow, &poissonDisk);
     if(num_blockers < 1.) {
         // There are no occluders so early out (this saves filtering)
         return 1.;
@@ -108,5 +109,5 @@ fn percent_closer_soft_shadow(
     let penumbra_ratio = penumbra_size(z_receiver, avg_blocker_depth);
     let filter_radius_uv = penumbra_ratio * info.light_uv * info.near_plane / z_receiver;
     // STEP 3: filtering
-    return pcf_filter(uv, z_receiver, filter_radius_uv, light_id, t_shadow, sampler_shadow_comparison, &poissonDisk);
+    return pcf_filter(uv, z_receiver, filter_radius_uv, layer, info, t_shadow, sampler_shadow_comparison, &poissonDisk);
 }
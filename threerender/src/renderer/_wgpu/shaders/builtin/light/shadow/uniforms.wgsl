@@ -1,14 +1,34 @@
 struct UniformShadow {
-    projection: mat4x4<f32>,
+    // Only the first projection is used without the cascades.
+    projections: array<mat4x4<f32>, 4>,
     // 0 or 1
     use_shadow: u32,
     opacity: f32,
 
     // 0: Normal shadow
     // 1: PCSS
+    // 2: Variance
     shadow_type: u32,
 
     // For PCSS
     light_uv: f32,
-    near_plane: f32,    
+    near_plane: f32,
+
+    // The first layer of the shadow map
+    layer: u32,
+    // For cascaded shadow
+    cascade_count: u32,
+    cascade_blend: f32,
+
+    // For point light
+    far_plane: f32,
+    // The index of the cube map, or -1.
+    cube: i32,
+
+    // The area of the layer used by the light in UV.
+    map_scale: vec2<f32>,
+    normal_offset: f32,
+    // The number of the texels on each side of the PCF kernel
+    pcf_kernel_size: u32,
+    padding: vec2<f32>,
 }
@@ -7,3 +7,17 @@ fn shadow_coords(homogeneous_coords: vec4<f32>) -> vec3<f32> {
     let depth = homogeneous_coords.z * proj_correction;
     return vec3(light_local, depth);
 }
+
+// The light may use only the part of the layer if its resolution is smaller than the shadow map.
+fn shadow_uv(uv: vec2<f32>, info: UniformShadow) -> vec2<f32> {
+    return clamp(uv, vec2(0.0), vec2(1.0)) * info.map_scale;
+}
+
+// The offsets of the PCF kernel are centered on the texel.
+// `texel` is the size of the texel in the layer, and the offsets are in the UV of the light,
+// which is scaled by `shadow_uv` afterwards.
+fn pcf_offset(i: u32, info: UniformShadow, texel: vec2<f32>) -> vec2<f32> {
+    let size = max(info.pcf_kernel_size, 1u);
+    let center = f32(size - 1u) * 0.5;
+    return (vec2(f32(i % size), f32(i / size)) - center) * texel / info.map_scale;
+}
@@ -0,0 +1,39 @@
+// The ratio of the penumbra cut off to reduce the light bleeding.
+const VARIANCE_LIGHT_BLEEDING_REDUCTION = 0.3;
+const VARIANCE_MIN_VARIANCE = 0.0001;
+
+// Returns the upper bound of the probability that the receiver is lit.
+fn chebyshev_upper_bound(moments: vec2<f32>, mean: f32) -> f32 {
+    if mean <= moments.x {
+        return 1.0;
+    }
+    let variance = max(moments.y - moments.x * moments.x, VARIANCE_MIN_VARIANCE * moments.x * moments.x);
+    let d = mean - moments.x;
+    let p_max = variance / (variance + d * d);
+    return clamp((p_max - VARIANCE_LIGHT_BLEEDING_REDUCTION) / (1.0 - VARIANCE_LIGHT_BLEEDING_REDUCTION), 0.0, 1.0);
+}
+
+// Ref: https://developer.nvidia.com/gpugems/gpugems3/part-ii-light-and-shadows/chapter-8-summed-area-variance-shadow-maps
+fn variance_shadow(
+    layer: u32,
+    homogeneous_coords: vec4<f32>,
+    info: UniformShadow,
+    t_shadow_variance: texture_2d_array<f32>,
+    sampler_shadow: sampler,
+) -> f32 {
+    let coords = shadow_coords(homogeneous_coords);
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_shadow_variance));
+    let size = max(info.pcf_kernel_size, 1u);
+    // The moments are filtered instead of the results of the comparison.
+    var moments = vec4(0.0);
+    for (var i = 0u; i < size * size; i += 1u) {
+        let uv = shadow_uv(coords.xy + pcf_offset(i, info, texel), info);
+        moments += textureSampleLevel(t_shadow_variance, sampler_shadow, uv, i32(layer), 0.0);
+    }
+    moments /= f32(size * size);
+
+    let warped = evsm_warp(coords.z);
+    let positive = chebyshev_upper_bound(moments.xy, warped.x);
+    let negative = chebyshev_upper_bound(moments.zw, warped.y);
+    return min(positive, negative);
+}
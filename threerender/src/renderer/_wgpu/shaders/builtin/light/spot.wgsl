@@ -0,0 +1,20 @@
+// `light_direction` is the world space direction from the surface to the light.
+fn calc_spot_cone(light_direction: vec3<f32>, ulight: UniformLight) -> f32 {
+    let cos_angle = dot(-light_direction, ulight.direction);
+    // Avoid the undefined result of smoothstep when both edges are same.
+    return smoothstep(ulight.outer_cone, max(ulight.inner_cone, ulight.outer_cone + 0.0001), cos_angle);
+}
+
+fn calc_spot_light(world_normal: vec3<f32>, light_normal: vec3<f32>, light_direction: vec3<f32>, distance: f32, ulight: UniformLight) -> SpotLight {
+    let attenuation = calc_distance_attenuation(distance, ulight) * calc_spot_cone(light_direction, ulight);
+    let blightness = vec4<f32>(ulight.brightness * attenuation);
+
+    let diffuse = max(dot(world_normal, light_normal), 0.0) * ulight.color * blightness;
+
+    var s: SpotLight;
+    s.color = diffuse;
+    s.normal = light_normal;
+    s.attenuation = attenuation;
+
+    return s;
+}
@@ -6,3 +6,15 @@ struct DirectionalLight {
 struct HemisphereLight {
     color: vec4<f32>,
 }
+
+struct PointLight {
+    color: vec4<f32>,
+    normal: vec3<f32>,
+    attenuation: f32,
+}
+
+struct SpotLight {
+    color: vec4<f32>,
+    normal: vec3<f32>,
+    attenuation: f32,
+}
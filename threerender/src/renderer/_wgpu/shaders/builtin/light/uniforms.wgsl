@@ -12,7 +12,17 @@ struct UniformLight {
     // 1: directional light
     // 2: hemisphere light
     // 3: ambient light
+    // 4: point light
+    // 5: spot light
     model: u32,
+    // For point and spot light
+    range: f32,
+    decay: f32,
+    // For spot light
+    direction: vec3<f32>,
+    // Cosine of the cone angles
+    inner_cone: f32,
+    outer_cone: f32,
     hemisphere: UniformHemisphereLight,
     shadow: UniformShadow,
 }
@@ -0,0 +1,56 @@
+struct PBR {
+  metalness: f32,
+  roughness: f32,
+  // 0: Unlit
+  // 1: Lambert
+  // 2: Phong
+  // 3: PBR
+  shading: u32,
+  padding: f32,
+}
+
+const PBR_PI = 3.14159265359;
+
+// Normal distribution function of GGX
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a = roughness * roughness;
+  let a2 = a * a;
+  let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+  return a2 / (PBR_PI * d * d);
+}
+
+// Geometry function of Schlick-GGX for the direct lighting
+fn geometry_schlick_ggx(n_dot_v: f32, roughness: f32) -> f32 {
+  let r = roughness + 1.0;
+  let k = r * r / 8.0;
+  return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+  return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Cook-Torrance BRDF for the direct light.
+// The result is multiplied by PI to keep the same brightness as the lambert diffuse.
+// Ref: https://learnopengl.com/PBR/Lighting
+fn calc_pbr_reflectance(normal: vec3<f32>, view_normal: vec3<f32>, light_normal: vec3<f32>, radiance: vec3<f32>, base_color: vec3<f32>, pbr: PBR) -> vec3<f32> {
+  let roughness = clamp(pbr.roughness, 0.04, 1.0);
+  let half_normal = normalize(view_normal + light_normal);
+
+  let n_dot_v = max(dot(normal, view_normal), 0.0001);
+  let n_dot_l = max(dot(normal, light_normal), 0.0);
+  let n_dot_h = max(dot(normal, half_normal), 0.0);
+  let h_dot_v = max(dot(half_normal, view_normal), 0.0);
+
+  // Dielectric surfaces reflect about 4% of the light.
+  let f0 = mix(vec3<f32>(0.04), base_color, pbr.metalness);
+
+  let d = distribution_ggx(n_dot_h, roughness);
+  let g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+  let f = fresnel_schlick(h_dot_v, f0);
+
+  let specular = d * g * f / (4.0 * n_dot_v * max(n_dot_l, 0.0001));
+  let diffuse = (vec3<f32>(1.0) - f) * (1.0 - pbr.metalness) * base_color / PBR_PI;
+
+  return (diffuse + specular) * radiance * n_dot_l * PBR_PI;
+}
@@ -0,0 +1,15 @@
+// This is used for clearing the viewport, since the load operation of the render pass clears the whole target.
+// The color is given by the blend constant.
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> @builtin(position) vec4<f32> {
+    // The triangle covers the whole clip space.
+    let uv = vec2<f32>(f32((idx << 1u) & 2u), f32(idx & 2u));
+    // The depth is reset to the far plane.
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
@@ -1,6 +1,8 @@
 #include builtin::math
 #include builtin::light
 #include builtin::reflection
+#include builtin::pbr
+#include builtin::fog
 
 // Variables for vertex
 
@@ -8,6 +10,8 @@ struct Scene {
     model: mat4x4<f32>,
     eye: vec3<f32>,
     num_lights: u32,
+    inv_model: mat4x4<f32>,
+    fog: Fog,
 }
 
 @group(0)
@@ -23,6 +27,9 @@ struct Entity {
     // 0 or 1
     receive_shadow: vec4<u32>,
     reflection: Reflection,
+    pbr: PBR,
+    // 0 or 1. The back face is lit with the flipped normal if 1.
+    double_sided: vec4<u32>,
 }
 
 @group(1)
@@ -50,6 +57,17 @@ fn convert_normal_transform() -> mat3x3<f32> {
     );
 }
 
+struct Instance {
+    @location(5) transform0: vec4<f32>,
+    @location(6) transform1: vec4<f32>,
+    @location(7) transform2: vec4<f32>,
+    @location(8) transform3: vec4<f32>,
+    @location(9) normal_transform0: vec3<f32>,
+    @location(10) normal_transform1: vec3<f32>,
+    @location(11) normal_transform2: vec3<f32>,
+    @location(12) color: vec4<f32>,
+}
+
 // Vertex entry point
 
 @vertex
@@ -59,8 +77,21 @@ fn vs_main(
     @location(2) tex_coords: vec2<f32>,
     @location(3) tangent: vec3<f32>,
     @location(4) bitangent: vec3<f32>,
+    instance: Instance,
 ) -> VertexOutput {
-    let normal_transform = convert_normal_transform();
+    let instance_transform = mat4x4<f32>(
+        instance.transform0,
+        instance.transform1,
+        instance.transform2,
+        instance.transform3,
+    );
+    let instance_normal_transform = mat3x3<f32>(
+        instance.normal_transform0,
+        instance.normal_transform1,
+        instance.normal_transform2,
+    );
+    let transform = entity.transform * instance_transform;
+    let normal_transform = convert_normal_transform() * instance_normal_transform;
     let has_normal_map = entity.normal_idx.x != -1;
     var tangent_matrix: mat3x3<f32>;
     if has_normal_map {
@@ -77,7 +108,7 @@ fn vs_main(
     var local_normal = normalize(normal_transform * normal);
     var world_view = vec4(uscene.eye, 1.0);
 
-    let local_position = entity.transform * position;
+    let local_position = transform * position;
 
     var tangent_or_local_position: vec4<f32>;
     if has_normal_map {
@@ -87,11 +118,11 @@ fn vs_main(
         tangent_or_local_position = local_position;
     };
 
-    let entity_position = uscene.model * entity.transform * position;
+    let entity_position = uscene.model * transform * position;
 
     var result: VertexOutput;
 
-    result.color = entity.color;
+    result.color = entity.color * instance.color;
     result.local_normal = local_normal;
     result.local_position = local_position;
     result.tangent_or_local_position = tangent_or_local_position;
@@ -123,6 +154,19 @@ var sampler_shadow: sampler;
 @binding(2)
 var sampler_shadow_comparison: sampler_comparison;
 
+// Only a single cube is bound if the cube array textures are not supported.
+@group(3)
+@binding(3)
+#ifdef CUBE_ARRAY
+var t_shadow_cube: texture_depth_cube_array;
+#else
+var t_shadow_cube: texture_depth_cube;
+#end
+
+@group(3)
+@binding(4)
+var t_shadow_variance: texture_2d_array<f32>;
+
 #ifdef HAS_TEXTURE
 @group(4)
 @binding(0)
@@ -132,10 +176,48 @@ var texs: binding_array<texture_2d<f32>>;
 var sams: binding_array<sampler>;
 #end
 
+// `normal` is the normal of the surface in the world space.
+fn calc_light_visibility(ulight: UniformLight, local_position: vec4<f32>, normal: vec3<f32>) -> f32 {
+    if ulight.shadow.use_shadow == 1u && entity.receive_shadow.x == 1u {
+        var mask: f32;
+        // Point light
+        if ulight.model == 4u {
+            mask = calc_point_shadow_mask(
+                ulight.position,
+                local_position,
+                normal,
+                ulight.shadow,
+                t_shadow_cube,
+                sampler_shadow_comparison,
+            );
+        } else {
+            mask = calc_shadow_mask(
+                local_position,
+                normal,
+                ulight.shadow,
+                t_shadow,
+                t_shadow_variance,
+                sampler_shadow,
+                sampler_shadow_comparison,
+            );
+        }
+        return clamp(mask + (1. - ulight.shadow.opacity), 0.0, 1.0);
+    }
+    return 1.0;
+}
+
 // Fragment entry point
 
 @fragment
-fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+fn fs_main(vertex: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    let color = calc_color(vertex, front_facing);
+    // `local_position` is in the world space.
+    let position = vertex.local_position.xyz / vertex.local_position.w;
+    let fog = calc_fog(uscene.fog, distance(uscene.eye, position), position.y);
+    return vec4(mix(color.rgb, uscene.fog.color, fog), color.a);
+}
+
+fn calc_color(vertex: VertexOutput, front_facing: bool) -> vec4<f32> {
     let normal_transform = convert_normal_transform();
     let has_normal_map = entity.normal_idx.x != -1;
     var normal: vec3<f32> = vertex.local_normal;
@@ -150,14 +232,43 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
         );
     }
 #end
+    // The back face of the double sided entity faces the opposite side.
+    let flip_normal = entity.double_sided.x == 1u && !front_facing;
+    if flip_normal {
+        normal = -normal;
+    }
+
+    var base_color = vertex.color;
+#ifdef HAS_TEXTURE
+    if entity.tex_idx.x != -1 {
+        base_color *= textureSample(texs[entity.tex_idx.x], sams[entity.tex_idx.x], vertex.tex_coords);
+    }
+#end
+
+    // Unlit
+    if entity.pbr.shading == 0u {
+        return base_color;
+    }
+
+    let use_specular = entity.pbr.shading == 2u;
+    let use_pbr = entity.pbr.shading == 3u;
 
     var color: vec4<f32> = vec4(0.);
+    // The normal map is in the tangent space, so the shadow uses the normal of the vertex.
+    var surface_normal = normalize(vertex.local_normal);
+    if flip_normal {
+        surface_normal = -surface_normal;
+    }
     let camera_position = vertex.world_view;
+    let view_normal = normalize(calc_affine_normal(camera_position, vertex.tangent_or_local_position).xyz);
     for(var i = 0u; i < min(uscene.num_lights, #{MAX_LIGHT_NUM}u); i += 1u) {
         let ulight = ulights[i];
         if ulight.model != 0u {
             let light_position = vec4<f32>(ulight.position, 1.0);
             var light_normal: vec3<f32> = calc_affine_normal(light_position, vertex.local_position).xyz;
+            // Keep the world space vector for the attenuation of the point and spot light.
+            let light_distance = length(light_normal);
+            let world_light_normal = normalize(light_normal);
             if has_normal_map {
                 light_normal = tangent_matrix * light_normal;
             }
@@ -166,21 +277,69 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
             // Directional light
             if ulight.model == 1u {
                 let light = calc_directional_light(normal, light_normal, ulight);
+                let visibility = calc_light_visibility(ulight, vertex.local_position, surface_normal);
+
+                if use_pbr {
+                    let radiance = ulight.color.xyz * ulight.brightness * visibility;
+                    color += vec4(calc_pbr_reflectance(normal, view_normal, light_normal, radiance, base_color.xyz, entity.pbr), 0.0);
+                } else {
+                    var reflection = vec4(0.);
+                    if use_specular {
+                        reflection = calc_specular_reflection(camera_position, vertex.tangent_or_local_position, normal, light_normal, entity.reflection);
+                    }
+
+                    // shadow
+                    if ulight.shadow.use_shadow == 1u && entity.receive_shadow.x == 1u {
+                        color += vec4(visibility * light.color.xyz, 1.0) + reflection;
+                    } else {
+                        color += light.color + reflection;
+                    }
+                }
+            }
+
+            // Point light
+            if ulight.model == 4u {
+                let light = calc_point_light(normal, light_normal, light_distance, ulight);
+                let visibility = calc_light_visibility(ulight, vertex.local_position, surface_normal);
+
+                if use_pbr {
+                    let radiance = ulight.color.xyz * ulight.brightness * light.attenuation * visibility;
+                    color += vec4(calc_pbr_reflectance(normal, view_normal, light_normal, radiance, base_color.xyz, entity.pbr), 0.0);
+                } else {
+                    var reflection = vec4(0.);
+                    if use_specular {
+                        reflection = calc_specular_reflection(camera_position, vertex.tangent_or_local_position, normal, light_normal, entity.reflection) * light.attenuation;
+                    }
+
+                    // shadow
+                    if ulight.shadow.use_shadow == 1u && entity.receive_shadow.x == 1u {
+                        color += vec4(visibility * light.color.xyz, 1.0) + reflection;
+                    } else {
+                        color += light.color + reflection;
+                    }
+                }
+            }
+
+            // Spot light
+            if ulight.model == 5u {
+                let light = calc_spot_light(normal, light_normal, world_light_normal, light_distance, ulight);
+                let visibility = calc_light_visibility(ulight, vertex.local_position, surface_normal);
 
-                let reflection = calc_specular_reflection(camera_position, vertex.tangent_or_local_position, normal, light_normal, entity.reflection);
-
-                // shadow
-                if ulight.shadow.use_shadow == 1u && entity.receive_shadow.x == 1u {
-                    color += vec4(clamp(calc_shadow_mask(
-                        i,
-                        ulight.shadow.projection * vertex.local_position,
-                        ulight.shadow,
-                        t_shadow,
-                        sampler_shadow,
-                        sampler_shadow_comparison,
-                    ) + (1. - ulight.shadow.opacity), 0.0, 1.0) * light.color.xyz, 1.0) + reflection;
+                if use_pbr {
+                    let radiance = ulight.color.xyz * ulight.brightness * light.attenuation * visibility;
+                    color += vec4(calc_pbr_reflectance(normal, view_normal, light_normal, radiance, base_color.xyz, entity.pbr), 0.0);
                 } else {
-                    color += light.color + reflection;
+                    var reflection = vec4(0.);
+                    if use_specular {
+                        reflection = calc_specular_reflection(camera_position, vertex.tangent_or_local_position, normal, light_normal, entity.reflection) * light.attenuation;
+                    }
+
+                    // shadow
+                    if ulight.shadow.use_shadow == 1u && entity.receive_shadow.x == 1u {
+                        color += vec4(visibility * light.color.xyz, 1.0) + reflection;
+                    } else {
+                        color += light.color + reflection;
+                    }
                 }
             }
 
@@ -188,23 +347,27 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
             if ulight.model == 2u {
                 let light = calc_hemisphere_light(light_normal, normal, ulight);
 
-                color += light.color;
+                if use_pbr {
+                    color += vec4(light.color.xyz * base_color.xyz, 0.0);
+                } else {
+                    color += light.color;
+                }
             }
 
             // Ambient light
             if ulight.model == 3u {
-                color += ulight.color;
+                if use_pbr {
+                    color += vec4(ulight.color.xyz * base_color.xyz, 0.0);
+                } else {
+                    color += ulight.color;
+                }
             }
         }
     }
 
-    color *= vertex.color;
-
-#ifdef HAS_TEXTURE
-    if entity.tex_idx.x != -1 {
-        color *= textureSample(texs[entity.tex_idx.x], sams[entity.tex_idx.x], vertex.tex_coords);
+    if use_pbr {
+        return vec4(color.xyz, base_color.a);
     }
-#end
 
-    return color;
-}
\ No newline at end of file
+    return vec4((color * base_color).xyz, base_color.a);
+}
@@ -0,0 +1,36 @@
+// The common declarations of the post-processing passes.
+// This is prepended to the source of each pass.
+
+struct PostProcess {
+    // The size of a pixel in the texture coordinates.
+    texel_size: vec2<f32>,
+    exposure: f32,
+    // 0: None, 1: Reinhard, 2: ACES
+    tone_mapping: u32,
+}
+
+@group(0)
+@binding(0)
+var t_input: texture_2d<f32>;
+@group(0)
+@binding(1)
+var s_input: sampler;
+@group(0)
+@binding(2)
+var<uniform> post_process: PostProcess;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    // The triangle covers the whole clip space.
+    let uv = vec2<f32>(f32((idx << 1u) & 2u), f32(idx & 2u));
+
+    var result: VertexOutput;
+    result.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
@@ -0,0 +1,51 @@
+// Based on FXAA by Timothy Lottes.
+
+const FXAA_REDUCE_MIN: f32 = 0.0078125;
+const FXAA_REDUCE_MUL: f32 = 0.125;
+const FXAA_SPAN_MAX: f32 = 8.0;
+
+// The luma is compressed since the input is HDR.
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color / (1.0 + color), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = post_process.texel_size;
+    let uv = vertex.uv;
+
+    let color = textureSample(t_input, s_input, uv);
+    let luma_nw = luma(textureSample(t_input, s_input, uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+    let luma_ne = luma(textureSample(t_input, s_input, uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+    let luma_sw = luma(textureSample(t_input, s_input, uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+    let luma_se = luma(textureSample(t_input, s_input, uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+    let luma_m = luma(color.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur along the edge.
+    var dir = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * (0.25 * FXAA_REDUCE_MUL), FXAA_REDUCE_MIN);
+    let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX)) * texel;
+
+    let rgb_a = 0.5 * (
+        textureSample(t_input, s_input, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        textureSample(t_input, s_input, uv + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let rgb_b = rgb_a * 0.5 + 0.25 * (
+        textureSample(t_input, s_input, uv + dir * -0.5).rgb +
+        textureSample(t_input, s_input, uv + dir * 0.5).rgb
+    );
+
+    // Use the narrower blur if the wider one picks the other edge.
+    let luma_b = luma(rgb_b);
+    if luma_b < luma_min || luma_b > luma_max {
+        return vec4<f32>(rgb_a, color.a);
+    }
+    return vec4<f32>(rgb_b, color.a);
+}
@@ -0,0 +1,31 @@
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// The fitted curve by Krzysztof Narkowicz.
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_input, s_input, vertex.uv);
+    var rgb = color.rgb * post_process.exposure;
+    switch post_process.tone_mapping {
+        case 1u: {
+            rgb = reinhard(rgb);
+        }
+        case 2u: {
+            rgb = aces(rgb);
+        }
+        default: {
+            rgb = clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+    return vec4<f32>(rgb, color.a);
+}
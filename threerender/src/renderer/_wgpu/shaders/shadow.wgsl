@@ -1,5 +1,7 @@
 // This is used for baking shadow as texture.
 
+#include builtin::light::shadow::evsm
+
 @group(0)
 @binding(0)
 var<uniform> umodel: mat4x4<f32>;
@@ -12,12 +14,34 @@ struct Entity {
 @binding(0)
 var<uniform> entity: Entity;
 
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
 // For shadow
 @vertex
 fn vs_bake(
     @location(0) position: vec4<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) _tex_coords: vec2<f32>,
-) -> @builtin(position) vec4<f32> {
-    return umodel * entity.transform * position;
+    @location(5) instance_transform0: vec4<f32>,
+    @location(6) instance_transform1: vec4<f32>,
+    @location(7) instance_transform2: vec4<f32>,
+    @location(8) instance_transform3: vec4<f32>,
+) -> VertexOutput {
+    let instance_transform = mat4x4<f32>(
+        instance_transform0,
+        instance_transform1,
+        instance_transform2,
+        instance_transform3,
+    );
+    var result: VertexOutput;
+    result.position = umodel * entity.transform * instance_transform * position;
+    return result;
+}
+
+// For the variance shadow map
+@fragment
+fn fs_variance(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return evsm_moments(vertex.position.z);
 }
@@ -0,0 +1,62 @@
+#include builtin::fog
+
+// This is used for drawing the cubemap behind all entities.
+// Without the cubemap, only the fog is blended over the background color.
+
+struct Scene {
+    model: mat4x4<f32>,
+    eye: vec3<f32>,
+    num_lights: u32,
+    inv_model: mat4x4<f32>,
+    fog: Fog,
+}
+
+@group(0)
+@binding(0)
+var<uniform> uscene: Scene;
+
+#ifdef HAS_TEXTURE
+@group(1)
+@binding(0)
+var t_skybox: texture_cube<f32>;
+@group(1)
+@binding(1)
+var s_skybox: sampler;
+#end
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) clip_position: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    // The triangle covers the whole clip space.
+    let uv = vec2<f32>(f32((idx << 1u) & 2u), f32(idx & 2u));
+    let position = uv * 2.0 - 1.0;
+
+    var result: VertexOutput;
+    result.position = vec4<f32>(position, 1.0, 1.0);
+    result.clip_position = position;
+    return result;
+}
+
+fn unproject(position: vec2<f32>, depth: f32) -> vec3<f32> {
+    let world = uscene.inv_model * vec4<f32>(position, depth, 1.0);
+    return world.xyz / world.w;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    // The background is fogged as if it is on the far plane.
+    let far = unproject(vertex.clip_position, 1.0);
+    let fog = calc_fog(uscene.fog, distance(uscene.eye, far), far.y);
+#ifdef HAS_TEXTURE
+    // The direction from the near plane to the far plane works for both projections.
+    let direction = far - unproject(vertex.clip_position, 0.0);
+    let color = textureSample(t_skybox, s_skybox, direction);
+    return vec4(mix(color.rgb, uscene.fog.color, fog), color.a);
+#else
+    return vec4(uscene.fog.color, fog);
+#end
+}
@@ -1,18 +1,21 @@
-use std::{borrow::Cow, collections::HashMap, mem, rc::Rc};
+use std::{borrow::Cow, collections::HashMap, mem};
 
 use glam::Mat4;
-use threerender_traits::entity::{EntityRendererState, RendererState};
+use threerender_traits::entity::EntityRendererState;
 use wgpu::{
-    util::align_to, vertex_attr_array, BindGroup, BindGroupLayout, Buffer, Device,
-    PrimitiveTopology, Queue, RenderPipeline, ShaderModule, TextureView,
+    util::align_to, vertex_attr_array, BindGroup, BindGroupLayout, Buffer, BufferAddress, Device,
+    PipelineLayout, PrimitiveTopology, Queue, RenderPipeline, ShaderModule, Texture, TextureView,
 };
 
-use crate::mesh::{PolygonMode, Topology, Vertex};
+use crate::{
+    mesh::{CullMode, FrontFace, PolygonMode, Topology, Vertex},
+    ShadowStyle, ShadowType,
+};
 
 use super::{
     processor::{ProcessOption, Processor},
-    scene::Scene,
-    uniform::ShadowEntityUniformBuffer,
+    scene::{Scene, ShadowLayers, ShadowUniform},
+    uniform::{InstanceBuffer, ShadowEntityUniformBuffer},
     RenderedEntity,
 };
 
@@ -21,11 +24,91 @@ pub(super) struct ShadowEntityUniform {
     pub(super) entity_bind_group: BindGroup,
 }
 
+impl ShadowEntityUniform {
+    // The uniform of each entity is placed at the same offset as the forward entity uniform,
+    // so `alignment` must be the alignment of the forward entity uniform.
+    fn new(
+        device: &Device,
+        entity_len: usize,
+        alignment: BufferAddress,
+    ) -> (BindGroupLayout, Self) {
+        let entity_uniform_size = mem::size_of::<ShadowEntityUniformBuffer>() as BufferAddress;
+        let entity_uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Transform Uniform Buffer"),
+            // Keep at least one entity to bind the buffer even if there is no entity.
+            size: entity_len.max(1) as BufferAddress * alignment,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (entity_bind_group_layout, entity_bind_group) =
+            RenderedEntity::make_bind_group(device, entity_uniform_size, &entity_uniform_buf);
+        (
+            entity_bind_group_layout,
+            Self {
+                entity_uniform_buf,
+                entity_bind_group,
+            },
+        )
+    }
+
+    // Recreate the uniform buffer and the bind group for the entity length.
+    // The uniforms of all entities need to be uploaded again after this.
+    pub(super) fn resize(&mut self, device: &Device, entity_len: usize, alignment: BufferAddress) {
+        let (_, entity) = Self::new(device, entity_len, alignment);
+        *self = entity;
+    }
+}
+
+// The pipelines differ by the options of the light.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct ShadowPipelineKey {
+    depth_bias: i32,
+    // The bits of `f32` to compare it as the key.
+    slope_scale_bias: u32,
+    // The front face is inverted, since the faces of the cube map are flipped vertically.
+    cube: bool,
+    // The moments are drawn into the color attachment for the variance shadow map.
+    variance: bool,
+}
+
+impl ShadowPipelineKey {
+    pub(super) fn new(style: &ShadowStyle, layers: &ShadowLayers) -> Self {
+        let cube = layers.cube.is_some();
+        Self {
+            depth_bias: style.depth_bias,
+            slope_scale_bias: style.slope_scale_bias.to_bits(),
+            cube,
+            variance: !cube && layers.variance && matches!(style.shadow_type, ShadowType::Variance),
+        }
+    }
+
+    pub(super) fn variance(&self) -> bool {
+        self.variance
+    }
+}
+
+// The attachments and the pipelines to bake a layer of the shadow map.
+pub(super) struct ShadowTarget<'a> {
+    pub(super) view: &'a TextureView,
+    pub(super) variance_view: Option<&'a TextureView>,
+    // The light is drawn into the top-left area of this size. `None` uses the whole layer.
+    pub(super) map_size: Option<(u32, u32)>,
+    pub(super) render_pipelines: &'a HashMap<EntityRendererState, RenderPipeline>,
+}
+
 pub(super) struct ShadowBaker {
-    pub(super) render_pipelines: HashMap<EntityRendererState, RenderPipeline>,
+    // The pipelines are created lazily for the options of the lights.
+    pub(super) render_pipelines:
+        HashMap<ShadowPipelineKey, HashMap<EntityRendererState, RenderPipeline>>,
+    pipeline_layout: PipelineLayout,
+    shader: ShaderModule,
+    states: Vec<EntityRendererState>,
     pub(super) entity: ShadowEntityUniform,
+    // The projections of the layers are followed by the projections of the cube faces.
     pub(super) camera: CameraUniform,
     pub(super) views: Vec<TextureView>,
+    pub(super) variance_views: Vec<TextureView>,
+    pub(super) cube_views: Vec<TextureView>,
 }
 
 impl ShadowBaker {
@@ -34,17 +117,16 @@ impl ShadowBaker {
     pub(super) fn new(
         device: &Device,
         entity_len: usize,
+        entity_uniform_alignment: BufferAddress,
         scene: &Scene,
-        states: Vec<RendererState>,
+        states: Vec<EntityRendererState>,
     ) -> Self {
-        let camera = CameraUniform::with_mat4(device, scene.light_uniform.len());
-        let (entity_uniform_size, entity_uniform_buf, _) = RenderedEntity::make_uniform(
+        let camera = CameraUniform::with_mat4(
             device,
-            entity_len,
-            mem::size_of::<ShadowEntityUniformBuffer>() as wgpu::BufferAddress,
+            (scene.shadow_uniform.layer_len + scene.shadow_uniform.cube_layer_len) as usize,
         );
-        let (entity_bind_group_layout, entity_bind_group) =
-            RenderedEntity::make_bind_group(device, entity_uniform_size, &entity_uniform_buf);
+        let (entity_bind_group_layout, entity) =
+            ShadowEntityUniform::new(device, entity_len, entity_uniform_alignment);
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("shadow"),
@@ -52,125 +134,154 @@ impl ShadowBaker {
             push_constant_ranges: &[],
         });
 
+        // Load the shaders from disk
         let mut processor = Processor::new(include_str!("shaders/shadow.wgsl"));
+        let source = processor.process(ProcessOption {
+            has_texture: false,
+            max_light_num: scene.scene.max_light_num,
+            cube_array: scene.shadow_uniform.cube_array,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
 
-        let mut lazy_load_shader =
-            |shader: &mut Option<Rc<ShaderModule>>, option: ProcessOption| match shader {
-                Some(ref s) => s.clone(),
-                None => {
-                    let source = processor.process(option);
-                    let s = Rc::new(device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                        label: None,
-                        source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
-                    }));
-                    *shader = Some(s.clone());
-                    s
-                }
-            };
-
-        // Load the shaders from disk
-        let mut shaders: Option<Rc<ShaderModule>> = None;
+        let create_views = |texture: &Texture, label, layer_len| {
+            (0..layer_len)
+                .map(|i| {
+                    texture.create_view(&wgpu::TextureViewDescriptor {
+                        label: Some(label),
+                        dimension: Some(wgpu::TextureViewDimension::D2),
+                        base_array_layer: i,
+                        array_layer_count: Some(1),
+                        ..Default::default()
+                    })
+                })
+                .collect()
+        };
+        let shadow_uniform = &scene.shadow_uniform;
+        let views = create_views(&shadow_uniform.texture, "shadow", shadow_uniform.layer_len);
+        let variance_views = create_views(
+            &shadow_uniform.variance_texture,
+            "shadow variance",
+            shadow_uniform.variance_texture.depth_or_array_layers(),
+        );
+        let cube_views = create_views(
+            &shadow_uniform.cube_texture,
+            "shadow cube",
+            shadow_uniform.cube_layer_len,
+        );
 
-        let mut render_pipelines = HashMap::new();
+        let mut baker = Self {
+            render_pipelines: HashMap::new(),
+            pipeline_layout,
+            shader,
+            states,
+            entity,
+            camera,
+            views,
+            variance_views,
+            cube_views,
+        };
+        baker.prepare_pipelines(device, scene);
+        baker
+    }
 
-        // TODO: commonize with renderer
-        for state in states {
-            let key = EntityRendererState::from_renderer_state(state);
-            if render_pipelines.get(&key).is_some() {
+    // Create the pipelines for the options of the lights which are not created yet.
+    pub(super) fn prepare_pipelines(&mut self, device: &Device, scene: &Scene) {
+        for (light, layers) in scene.scene.lights.iter().zip(&scene.shadow_uniform.layers) {
+            let key = match light.shadow() {
+                Some(style) => ShadowPipelineKey::new(style, layers),
+                None => continue,
+            };
+            if self.render_pipelines.contains_key(&key) {
                 continue;
             }
+            let mut pipelines = HashMap::new();
+            for state in &self.states {
+                if pipelines.contains_key(state) {
+                    continue;
+                }
+                pipelines.insert(state.clone(), self.create_pipeline(device, state, &key));
+            }
+            self.render_pipelines.insert(key, pipelines);
+        }
+    }
 
-            let shader = lazy_load_shader(
-                &mut shaders,
-                ProcessOption {
-                    has_texture: false,
-                    max_light_num: scene.scene.max_light_num,
-                },
-            );
-
-            let (vertex_buf_size, vertex_buf_attr) = (
-                mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                vertex_attr_array![0 => Float32x4, 1 => Float32x3, 2 => Float32x2].to_vec(),
-            );
-
-            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("ShadowBaker"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_bake",
-                    buffers: &[wgpu::VertexBufferLayout {
+    // TODO: commonize with renderer
+    fn create_pipeline(
+        &self,
+        device: &Device,
+        state: &EntityRendererState,
+        key: &ShadowPipelineKey,
+    ) -> RenderPipeline {
+        let (vertex_buf_size, vertex_buf_attr) = (
+            mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            vertex_attr_array![0 => Float32x4, 1 => Float32x3, 2 => Float32x2].to_vec(),
+        );
+
+        let front_face = match (&state.front_face, key.cube) {
+            (FrontFace::Ccw, false) | (FrontFace::Cw, true) => wgpu::FrontFace::Ccw,
+            (FrontFace::Cw, false) | (FrontFace::Ccw, true) => wgpu::FrontFace::Cw,
+        };
+        let variance_targets = [Some(ShadowUniform::VARIANCE_FORMAT.into())];
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ShadowBaker"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: "vs_bake",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
                         array_stride: vertex_buf_size,
                         step_mode: wgpu::VertexStepMode::Vertex,
                         attributes: &vertex_buf_attr,
-                    }],
-                },
-                fragment: None,
-                primitive: wgpu::PrimitiveState {
-                    topology: match &key.topology {
-                        Topology::PointList => PrimitiveTopology::PointList,
-                        Topology::LineList => PrimitiveTopology::LineList,
-                        Topology::TriangleList => PrimitiveTopology::TriangleList,
                     },
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: match &key.polygon_mode {
-                        PolygonMode::Fill => wgpu::PolygonMode::Fill,
-                        PolygonMode::Line => wgpu::PolygonMode::Line,
-                        PolygonMode::Point => wgpu::PolygonMode::Point,
-                    },
-                    unclipped_depth: device
-                        .features()
-                        .contains(wgpu::Features::DEPTH_CLIP_CONTROL),
-                    ..Default::default()
+                    InstanceBuffer::layout(&InstanceBuffer::SHADOW_ATTRIBUTES),
+                ],
+            },
+            fragment: key.variance.then_some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: "fs_variance",
+                targets: &variance_targets,
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: match &state.topology {
+                    Topology::PointList => PrimitiveTopology::PointList,
+                    Topology::LineList => PrimitiveTopology::LineList,
+                    Topology::TriangleList => PrimitiveTopology::TriangleList,
                 },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: Self::DEPTH_FORMAT,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::LessEqual,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState {
-                        constant: 2, // corresponds to bilinear filtering
-                        slope_scale: 2.0,
-                        clamp: 0.0,
-                    },
-                }),
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-            });
-
-            render_pipelines.insert(key, pipeline);
-        }
-
-        let mut views = vec![];
-
-        for i in 0..scene.light_uniform.len() {
-            let bake_view =
-                scene
-                    .shadow_uniform
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor {
-                        label: Some("shadow"),
-                        format: None,
-                        dimension: Some(wgpu::TextureViewDimension::D2),
-                        aspect: wgpu::TextureAspect::All,
-                        base_mip_level: 0,
-                        mip_level_count: None,
-                        base_array_layer: i as u32,
-                        array_layer_count: Some(1),
-                    });
-            views.push(bake_view);
-        }
-
-        Self {
-            render_pipelines,
-            entity: ShadowEntityUniform {
-                entity_uniform_buf,
-                entity_bind_group,
+                front_face,
+                cull_mode: match &state.cull_mode {
+                    CullMode::None => None,
+                    CullMode::Front => Some(wgpu::Face::Front),
+                    CullMode::Back => Some(wgpu::Face::Back),
+                },
+                polygon_mode: match &state.polygon_mode {
+                    PolygonMode::Fill => wgpu::PolygonMode::Fill,
+                    PolygonMode::Line => wgpu::PolygonMode::Line,
+                    PolygonMode::Point => wgpu::PolygonMode::Point,
+                },
+                unclipped_depth: device
+                    .features()
+                    .contains(wgpu::Features::DEPTH_CLIP_CONTROL),
+                ..Default::default()
             },
-            camera,
-            views,
-        }
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Self::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: key.depth_bias,
+                    slope_scale: f32::from_bits(key.slope_scale_bias),
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
     }
 }
 
@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+
+use threerender_traits::cubemap::Cubemap;
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue, RenderPass, RenderPipeline, TextureFormat};
+
+use super::{
+    processor::{ProcessOption, Processor},
+    scene::Scene,
+    texel, RenderedTexture,
+};
+
+// The cubemap drawn behind all entities.
+// Without the cubemap, the fog is blended over the background color.
+pub(super) struct Skybox {
+    pipeline: RenderPipeline,
+    bind_group: Option<BindGroup>,
+}
+
+impl Skybox {
+    pub(super) fn new(
+        device: &Device,
+        queue: &Queue,
+        cubemap: Option<&Cubemap>,
+        scene: &Scene,
+        format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let cubemap = cubemap.map(|cubemap| Self::make_cubemap(device, queue, cubemap, scene));
+
+        let source = Processor::new(include_str!("shaders/skybox.wgsl")).process(ProcessOption {
+            has_texture: cubemap.is_some(),
+            ..Default::default()
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Skybox"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+        let mut bind_group_layouts = vec![&scene.scene_uniform.bind_group_layout];
+        if let Some((layout, _)) = &cubemap {
+            bind_group_layouts.push(layout);
+        }
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+        let target = match cubemap {
+            Some(_) => format.into(),
+            // The alpha is the amount of the fog.
+            None => wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+        };
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target)],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // The skybox is drawn on the far plane after the opaque entities,
+            // so only the pixels which no entity covers are filled.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: scene.config.max_samples.min(scene.scene.msaa_samples),
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group: cubemap.map(|(_, bind_group)| bind_group),
+        }
+    }
+
+    fn make_cubemap(
+        device: &Device,
+        queue: &Queue,
+        cubemap: &Cubemap,
+        scene: &Scene,
+    ) -> (BindGroupLayout, BindGroup) {
+        let faces = cubemap.faces();
+        let size = cubemap.size();
+        let (texture_format, channel, upload_channel) = RenderedTexture::texture_format(
+            faces[0].format(),
+            device.features(),
+            scene.config.float32_filterable,
+        );
+        let bytes_per_pixel =
+            faces[0].bytes_per_pixel() / channel.size() as u32 * upload_channel.size() as u32;
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Skybox"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        for (i, face) in faces.iter().enumerate() {
+            let data = match channel == upload_channel {
+                true => Cow::Borrowed(face.data().as_slice()),
+                false => Cow::Owned(texel::convert(face.data(), channel, upload_channel)),
+            };
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: i as u32,
+                    },
+                    ..texture.as_image_copy()
+                },
+                &data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(size * bytes_per_pixel),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = RenderedTexture::make_sampler(&faces[0].sampler(), device);
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Skybox"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        (bind_group_layout, bind_group)
+    }
+
+    pub(super) fn draw<'a>(&'a self, rpass: &mut RenderPass<'a>, scene_bind_group: &'a BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, scene_bind_group, &[]);
+        if let Some(bind_group) = &self.bind_group {
+            rpass.set_bind_group(1, bind_group, &[]);
+        }
+        rpass.draw(0..3, 0..1);
+    }
+}
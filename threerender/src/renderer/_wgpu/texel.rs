@@ -0,0 +1,70 @@
+use half::f16;
+
+// The type of each channel in the texture data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ChannelType {
+    Unorm8,
+    Unorm16,
+    Float16,
+    Float32,
+}
+
+impl ChannelType {
+    pub(super) fn size(self) -> usize {
+        match self {
+            ChannelType::Unorm8 => 1,
+            ChannelType::Unorm16 | ChannelType::Float16 => 2,
+            ChannelType::Float32 => 4,
+        }
+    }
+
+    // Read the channel as the normalized value.
+    pub(super) fn read(self, buf: &[u8]) -> f32 {
+        match self {
+            ChannelType::Unorm8 => buf[0] as f32 / u8::MAX as f32,
+            ChannelType::Unorm16 => u16::from_le_bytes([buf[0], buf[1]]) as f32 / u16::MAX as f32,
+            ChannelType::Float16 => f16::from_le_bytes([buf[0], buf[1]]).to_f32(),
+            ChannelType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+        }
+    }
+
+    pub(super) fn write(self, value: f32, buf: &mut [u8]) {
+        match self {
+            ChannelType::Unorm8 => buf[0] = (value.clamp(0., 1.) * u8::MAX as f32).round() as u8,
+            ChannelType::Unorm16 => buf.copy_from_slice(
+                &((value.clamp(0., 1.) * u16::MAX as f32).round() as u16).to_le_bytes(),
+            ),
+            ChannelType::Float16 => buf.copy_from_slice(&f16::from_f32(value).to_le_bytes()),
+            ChannelType::Float32 => buf.copy_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+// Convert each channel of the data into the other type.
+pub(super) fn convert(data: &[u8], from: ChannelType, to: ChannelType) -> Vec<u8> {
+    let mut dst = vec![0; data.len() / from.size() * to.size()];
+    for (src, dst) in data
+        .chunks_exact(from.size())
+        .zip(dst.chunks_exact_mut(to.size()))
+    {
+        to.write(from.read(src), dst);
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let data = [0xff, 0xff, 0x00, 0x00];
+        let converted = convert(&data, ChannelType::Unorm16, ChannelType::Float16);
+        assert_eq!(ChannelType::Float16.read(&converted[0..2]), 1.);
+        assert_eq!(ChannelType::Float16.read(&converted[2..4]), 0.);
+
+        let data = 0.5_f32.to_le_bytes();
+        let converted = convert(&data, ChannelType::Float32, ChannelType::Float16);
+        assert_eq!(ChannelType::Float16.read(&converted), 0.5);
+    }
+}
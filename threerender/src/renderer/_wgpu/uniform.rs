@@ -1,6 +1,14 @@
+use std::mem;
+
 use bytemuck::{Pod, Zeroable};
+use glam::Mat3;
+use threerender_traits::entity::EntityInstance;
+use wgpu::{vertex_attr_array, BufferAddress, VertexAttribute, VertexBufferLayout};
 
-use super::scene::Reflection;
+use super::{
+    scene::{Pbr, Reflection},
+    unit::rgba_to_array,
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -14,6 +22,9 @@ pub(super) struct EntityUniformBuffer {
     pub(super) normal_idx: [i32; 4],
     pub(super) receive_shadow: [u32; 4],
     pub(super) reflection: Reflection,
+    pub(super) pbr: Pbr,
+    // First value is used
+    pub(super) double_sided: [u32; 4],
 }
 
 #[repr(C)]
@@ -21,3 +32,80 @@ pub(super) struct EntityUniformBuffer {
 pub(super) struct ShadowEntityUniformBuffer {
     pub(super) transform: [[f32; 4]; 4],
 }
+
+// This is used as the instance vertex buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(super) struct InstanceBuffer {
+    pub(super) transform: [[f32; 4]; 4],
+    pub(super) normal_transform: [[f32; 3]; 3],
+    pub(super) color: [f32; 4],
+}
+
+impl InstanceBuffer {
+    pub(super) const ATTRIBUTES: [VertexAttribute; 8] = vertex_attr_array![
+        5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4,
+        9 => Float32x3, 10 => Float32x3, 11 => Float32x3,
+        12 => Float32x4,
+    ];
+    // Only transform is used in shadow.
+    pub(super) const SHADOW_ATTRIBUTES: [VertexAttribute; 4] =
+        vertex_attr_array![5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4];
+
+    pub(super) fn from_instance(instance: &EntityInstance) -> Self {
+        let transform = instance.transform.as_mat4();
+        Self {
+            transform: transform.to_cols_array_2d(),
+            normal_transform: Mat3::from_mat4(transform)
+                .inverse()
+                .transpose()
+                .to_cols_array_2d(),
+            color: rgba_to_array(&instance.color),
+        }
+    }
+
+    pub(super) fn layout(attributes: &[VertexAttribute]) -> VertexBufferLayout<'_> {
+        VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use threerender_traits::material::{PBRStyle, ShadingModel};
+
+    use super::*;
+
+    #[test]
+    fn test_entity_uniform_layout() {
+        // Same as the layout of `Entity` in entity.wgsl.
+        let entity = EntityUniformBuffer::zeroed();
+        let offset = |field: *const u8| field as usize - &entity as *const _ as usize;
+        assert_eq!(mem::size_of::<Reflection>(), 16);
+        assert_eq!(mem::size_of::<Pbr>(), 16);
+        assert_eq!(offset(&entity.reflection as *const _ as *const u8), 192);
+        assert_eq!(offset(&entity.pbr as *const _ as *const u8), 208);
+        assert_eq!(offset(&entity.double_sided as *const _ as *const u8), 224);
+        assert_eq!(mem::size_of::<EntityUniformBuffer>(), 240);
+    }
+
+    #[test]
+    fn test_entity_uniform_shading() {
+        // The shader reads `Entity.pbr.shading` at this offset to select the lighting.
+        let shading_offset = 208 + 8;
+        let entity = EntityUniformBuffer {
+            pbr: Pbr::from_style(&ShadingModel::Phong, &PBRStyle::default()),
+            ..EntityUniformBuffer::zeroed()
+        };
+        let bytes = bytemuck::bytes_of(&entity);
+        let shading = u32::from_ne_bytes(
+            bytes[shading_offset..shading_offset + 4]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(shading, 2);
+    }
+}
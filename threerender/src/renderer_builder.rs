@@ -1,11 +1,14 @@
 use threerender_color::rgb::RGBA;
-use threerender_traits::entity::{EntityDescriptor, RendererState};
+use threerender_traits::{
+    cubemap::Cubemap,
+    entity::{EntityDescriptor, RendererState},
+};
 
 #[cfg(feature = "wgpu")]
 use crate::renderer::wgpu_builder::RendererSpecificAttributes;
-use crate::ShadowOptions;
+use crate::{FogStyle, PostProcessStyle, ShadowOptions};
 
-use super::scene::{CameraStyle, LightStyle, Scene};
+use super::scene::{CameraStyle, LightStyle, RenderTargetStyle, Scene, ViewStyle, Viewport};
 
 pub struct RendererBuilder {
     pub(super) entities: Vec<EntityDescriptor>,
@@ -93,6 +96,59 @@ impl RendererBuilder {
             .camera = camera;
     }
 
+    /// Set the viewport of the main camera.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.scene
+            .as_mut()
+            .expect("RendererBuilder has been consumed")
+            .viewport = viewport;
+    }
+
+    /// Add the camera which is drawn into the viewport after the main camera.
+    pub fn add_view(&mut self, view: ViewStyle) {
+        self.scene
+            .as_mut()
+            .expect("RendererBuilder has been consumed")
+            .views
+            .push(view);
+    }
+
+    /// Draw the cubemap behind all entities instead of the background color.
+    /// The skybox must be set before the renderer is created.
+    pub fn set_skybox(&mut self, cubemap: Cubemap) {
+        self.scene
+            .as_mut()
+            .expect("RendererBuilder has been consumed")
+            .skybox = Some(cubemap);
+    }
+
+    /// Render the scene into the HDR target and apply the post-processing.
+    pub fn set_post_process(&mut self, post_process: PostProcessStyle) {
+        self.scene
+            .as_mut()
+            .expect("RendererBuilder has been consumed")
+            .post_process = Some(post_process);
+    }
+
+    /// Mix the fog color into the entities and the background by the distance from the camera.
+    pub fn set_fog(&mut self, fog: FogStyle) {
+        self.scene
+            .as_mut()
+            .expect("RendererBuilder has been consumed")
+            .fog = Some(fog);
+    }
+
+    /// Add the offscreen render target.
+    /// Render targets must be added before the renderer is created,
+    /// since the textures are bound with the textures of the entities.
+    pub fn add_render_target(&mut self, target: RenderTargetStyle) {
+        self.scene
+            .as_mut()
+            .expect("RendererBuilder has been consumed")
+            .render_targets
+            .push(target);
+    }
+
     pub fn set_shadow_options(&mut self, options: ShadowOptions) {
         self.scene
             .as_mut()
@@ -1,5 +1,3 @@
-use std::f32::consts;
-
 use crate::math::{Affine3A, Mat4, Quat, Vec3};
 use getset::{Getters, MutGetters, Setters};
 use threerender_math::trs::{Rotation, Scale, Translation};
@@ -95,6 +93,56 @@ impl Translation for CameraUp {
     }
 }
 
+/// Projection of the camera.
+#[derive(Debug, Clone)]
+pub enum Projection {
+    /// `fov` is the vertical field of view in degrees.
+    Perspective {
+        fov: f32,
+    },
+    Orthographic(OrthographicExtent),
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Perspective { fov: 45. }
+    }
+}
+
+/// Visible area of the orthographic projection.
+#[derive(Debug, Clone)]
+pub enum OrthographicExtent {
+    /// The view volume is fixed even if the renderer is resized.
+    Bounds {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+    },
+    /// The vertical extent of the view volume centered on the camera.
+    /// The horizontal extent is calculated from the aspect ratio.
+    Zoom(f32),
+}
+
+impl OrthographicExtent {
+    // Returns `(left, right, bottom, top)`
+    fn bounds(&self, aspect: f32) -> (f32, f32, f32, f32) {
+        match self {
+            Self::Bounds {
+                left,
+                right,
+                bottom,
+                top,
+            } => (*left, *right, *bottom, *top),
+            Self::Zoom(extent) => {
+                let half_height = extent / 2.;
+                let half_width = half_height * aspect;
+                (-half_width, half_width, -half_height, half_height)
+            }
+        }
+    }
+}
+
 #[derive(Getters, MutGetters, Setters)]
 pub struct CameraStyle {
     #[getset(get = "pub", set = "pub")]
@@ -111,6 +159,8 @@ pub struct CameraStyle {
     pub center: CameraCenter,
     #[getset(get = "pub", get_mut = "pub")]
     pub up: CameraUp,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    pub projection: Projection,
 }
 
 impl Default for CameraStyle {
@@ -123,6 +173,7 @@ impl Default for CameraStyle {
             position: CameraPosition::new(3., 4., 5.),
             center: CameraCenter(Vec3::ZERO),
             up: CameraUp(Vec3::Y),
+            projection: Projection::default(),
         }
     }
 }
@@ -139,13 +190,21 @@ impl CameraStyle {
         Vec3::new(v.x, v.y, v.z)
     }
 
+    pub(crate) fn projection_mat4(&self) -> Mat4 {
+        let aspect = self.width / self.height;
+        match &self.projection {
+            Projection::Perspective { fov } => {
+                glam::Mat4::perspective_rh(fov.to_radians(), aspect, self.near, self.far)
+            }
+            Projection::Orthographic(extent) => {
+                let (left, right, bottom, top) = extent.bounds(aspect);
+                glam::Mat4::orthographic_rh(left, right, bottom, top, self.near, self.far)
+            }
+        }
+    }
+
     pub(crate) fn transform(&self) -> Mat4 {
-        let projection = glam::Mat4::perspective_rh(
-            consts::FRAC_PI_4,
-            self.width / self.height,
-            self.near,
-            self.far,
-        );
+        let projection = self.projection_mat4();
         let view = glam::Mat4::look_at_rh(
             self.calc_position_vec3().as_glam(),
             self.center.0.as_glam(),
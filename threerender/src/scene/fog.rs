@@ -0,0 +1,71 @@
+use getset::{Getters, MutGetters, Setters};
+use threerender_color::rgb::RGB;
+
+/// How the amount of the fog grows with the distance from the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+    /// The fog grows linearly from `start` to `end`.
+    /// The density is the amount of the fog at `end` and beyond.
+    Linear { start: f32, end: f32 },
+    /// `1 - exp(-density * distance)`
+    Exponential,
+    /// `1 - exp(-(density * distance)^2)`
+    ExponentialSquared,
+}
+
+impl FogMode {
+    pub(crate) fn as_u32(&self) -> u32 {
+        match self {
+            FogMode::Linear { .. } => 1,
+            FogMode::Exponential => 2,
+            FogMode::ExponentialSquared => 3,
+        }
+    }
+}
+
+/// The fog becomes thinner above `base_height`.
+/// The amount of the fog is multiplied by `exp(-falloff * (y - base_height))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeightFalloff {
+    pub base_height: f32,
+    pub falloff: f32,
+}
+
+impl HeightFalloff {
+    pub fn new(base_height: f32, falloff: f32) -> Self {
+        Self {
+            base_height,
+            falloff,
+        }
+    }
+}
+
+/// The color of the entities is mixed with the fog color by the distance from the camera.
+/// The background and the skybox are also tinted as if they were at the far plane.
+#[derive(Debug, Clone, Getters, MutGetters, Setters)]
+pub struct FogStyle {
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    mode: FogMode,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    color: RGB,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    density: f32,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    height_falloff: Option<HeightFalloff>,
+}
+
+impl FogStyle {
+    pub fn new(mode: FogMode, color: RGB, density: f32) -> Self {
+        Self {
+            mode,
+            color,
+            density,
+            height_falloff: None,
+        }
+    }
+
+    pub fn with_height_falloff(mut self, height_falloff: HeightFalloff) -> Self {
+        self.height_falloff = Some(height_falloff);
+        self
+    }
+}
@@ -13,6 +13,8 @@ pub enum LightModel {
     Directional,
     Hemisphere,
     Ambient,
+    Point,
+    Spot,
 }
 
 #[derive(Clone)]
@@ -30,6 +32,58 @@ impl Default for HemisphereLightStyle {
     }
 }
 
+/// Defines how a point light fades along the distance.
+#[derive(Clone, Getters, MutGetters, Setters)]
+pub struct PointLightStyle {
+    /// The distance at which the light is cut off. `0.` means no limit.
+    #[getset(get = "pub", set = "pub")]
+    pub range: f32,
+    /// The amount the light dims along the distance. `2.` is physically correct.
+    #[getset(get = "pub", set = "pub")]
+    pub decay: f32,
+}
+
+impl Default for PointLightStyle {
+    fn default() -> Self {
+        Self {
+            range: 0.,
+            decay: 2.,
+        }
+    }
+}
+
+/// Defines the cone and the attenuation of a spot light.
+#[derive(Clone, Getters, MutGetters, Setters)]
+pub struct SpotLightStyle {
+    /// The distance at which the light is cut off. `0.` means no limit.
+    #[getset(get = "pub", set = "pub")]
+    pub range: f32,
+    /// The amount the light dims along the distance. `2.` is physically correct.
+    #[getset(get = "pub", set = "pub")]
+    pub decay: f32,
+    /// The axis of the cone. This is rotated by the rotation of `LightBaseStyle`.
+    #[getset(get = "pub", set = "pub")]
+    pub direction: Vec3,
+    /// The angle in degrees from the axis where the light starts to fade out.
+    #[getset(get = "pub", set = "pub")]
+    pub inner_angle: f32,
+    /// The angle in degrees from the axis where the light is cut off.
+    #[getset(get = "pub", set = "pub")]
+    pub outer_angle: f32,
+}
+
+impl Default for SpotLightStyle {
+    fn default() -> Self {
+        Self {
+            range: 0.,
+            decay: 2.,
+            direction: Vec3::new(0., -1., 0.),
+            inner_angle: 20.,
+            outer_angle: 30.,
+        }
+    }
+}
+
 #[derive(Getters, MutGetters, Setters)]
 pub struct LightBaseStyle {
     // The alpha chanel is always ignored. This is to align buffer for wgsl.
@@ -70,18 +124,24 @@ impl Default for LightBaseStyle {
     }
 }
 
-#[derive(Default, Getters, MutGetters)]
+#[derive(Default, Getters)]
 pub struct LightStyle {
     #[getset(get = "pub", set = "pub")]
     id: String,
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     base: LightBaseStyle,
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     hemisphere: Option<HemisphereLightStyle>,
     #[getset(get = "pub")]
+    point: Option<PointLightStyle>,
+    #[getset(get = "pub")]
+    spot: Option<SpotLightStyle>,
+    #[getset(get = "pub")]
     model: LightModel,
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     shadow: Option<ShadowStyle>,
+    // Incremented whenever the light may be changed, so only the changed lights are recomputed.
+    pub(crate) generation: u64,
 }
 
 impl LightStyle {
@@ -104,7 +164,7 @@ impl LightStyle {
             },
             hemisphere: Some(hemisphere),
             model: LightModel::Hemisphere,
-            shadow: None,
+            ..Default::default()
         }
     }
 
@@ -119,4 +179,66 @@ impl LightStyle {
             ..Default::default()
         }
     }
+
+    /// Shadow of the point light is baked into the cube map around the light.
+    /// Only `near`, `far`, `opacity` and `shadow_type` of the `ShadowStyle` are used.
+    /// If the cube array textures are not supported, e.g. on WebGL2,
+    /// only the first point light with the shadow casts it.
+    pub fn with_point(
+        id: String,
+        base: LightBaseStyle,
+        point: PointLightStyle,
+        shadow: Option<ShadowStyle>,
+    ) -> Self {
+        Self {
+            id,
+            base,
+            point: Some(point),
+            model: LightModel::Point,
+            shadow,
+            ..Default::default()
+        }
+    }
+
+    /// Shadow of the spot light is projected by the `ShadowStyle` as same as the directional light.
+    pub fn with_spot(
+        id: String,
+        base: LightBaseStyle,
+        spot: SpotLightStyle,
+        shadow: Option<ShadowStyle>,
+    ) -> Self {
+        Self {
+            id,
+            base,
+            spot: Some(spot),
+            model: LightModel::Spot,
+            shadow,
+            ..Default::default()
+        }
+    }
+
+    pub fn base_mut(&mut self) -> &mut LightBaseStyle {
+        self.generation += 1;
+        &mut self.base
+    }
+
+    pub fn hemisphere_mut(&mut self) -> &mut Option<HemisphereLightStyle> {
+        self.generation += 1;
+        &mut self.hemisphere
+    }
+
+    pub fn point_mut(&mut self) -> &mut Option<PointLightStyle> {
+        self.generation += 1;
+        &mut self.point
+    }
+
+    pub fn spot_mut(&mut self) -> &mut Option<SpotLightStyle> {
+        self.generation += 1;
+        &mut self.spot
+    }
+
+    pub fn shadow_mut(&mut self) -> &mut Option<ShadowStyle> {
+        self.generation += 1;
+        &mut self.shadow
+    }
 }
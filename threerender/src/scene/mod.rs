@@ -1,5 +1,9 @@
 mod camera;
+mod fog;
 mod light;
+mod post_process;
+mod render_target;
+mod view;
 
 use std::f32::consts;
 
@@ -7,13 +11,18 @@ use getset::{Getters, MutGetters};
 use glam::Mat4;
 
 pub use camera::*;
+pub use fog::*;
 pub use light::*;
+pub use post_process::*;
+pub use render_target::*;
 use threerender_math::trs::{Rotation, Translation};
+use threerender_traits::cubemap::Cubemap;
+pub use view::*;
 
 #[derive(Getters, MutGetters)]
 pub struct Scene {
     pub(super) lights: Vec<LightStyle>,
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     pub(super) camera: CameraStyle,
     #[getset(get = "pub", get_mut = "pub")]
     pub(super) shadow_options: Option<ShadowOptions>,
@@ -21,6 +30,24 @@ pub struct Scene {
     pub(super) max_light_num: u32,
     #[getset(get = "pub", get_mut = "pub")]
     pub(super) msaa_samples: u32,
+    pub(super) render_targets: Vec<RenderTargetStyle>,
+    /// The viewport of `camera`. The size of `camera` follows the viewport in the next rendering.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub(super) viewport: Viewport,
+    pub(super) views: Vec<ViewStyle>,
+    /// Drawn behind all entities instead of the background color.
+    #[getset(get = "pub")]
+    pub(super) skybox: Option<Cubemap>,
+    /// `None` renders into the output directly without HDR.
+    /// The post-processing can't be enabled or disabled after the renderer is created.
+    #[getset(get = "pub")]
+    pub(super) post_process: Option<PostProcessStyle>,
+    #[getset(get = "pub")]
+    pub(super) fog: Option<FogStyle>,
+    // Incremented whenever the camera or the fog may be changed,
+    // so the uniforms are recomputed only for the changed ones.
+    pub(crate) camera_generation: u64,
+    pub(crate) fog_generation: u64,
 }
 
 impl Default for Scene {
@@ -33,11 +60,34 @@ impl Default for Scene {
             shadow_options: Default::default(),
             max_light_num: 10,
             msaa_samples: 1,
+            render_targets: vec![],
+            viewport: Default::default(),
+            views: vec![],
+            skybox: None,
+            post_process: None,
+            fog: None,
+            camera_generation: 0,
+            fog_generation: 0,
         }
     }
 }
 
 impl Scene {
+    pub fn camera_mut(&mut self) -> &mut CameraStyle {
+        self.camera_generation += 1;
+        &mut self.camera
+    }
+
+    /// Get the mutable style of the post-processing to change the exposure or the tone mapping.
+    pub fn post_process_mut(&mut self) -> Option<&mut PostProcessStyle> {
+        self.post_process.as_mut()
+    }
+
+    pub fn fog_mut(&mut self) -> &mut Option<FogStyle> {
+        self.fog_generation += 1;
+        &mut self.fog
+    }
+
     pub fn get_light(&self, id: &str) -> Option<&LightStyle> {
         self.lights.iter().find(|l| l.id() == id)
     }
@@ -45,11 +95,34 @@ impl Scene {
     pub fn get_light_mut(&mut self, id: &str) -> Option<&mut LightStyle> {
         self.lights.iter_mut().find(|l| l.id() == id)
     }
+
+    pub fn get_render_target(&self, id: &str) -> Option<&RenderTargetStyle> {
+        self.render_targets.iter().find(|t| t.id() == id)
+    }
+
+    pub fn get_render_target_mut(&mut self, id: &str) -> Option<&mut RenderTargetStyle> {
+        self.render_targets.iter_mut().find(|t| t.id() == id)
+    }
+
+    pub fn get_view(&self, id: &str) -> Option<&ViewStyle> {
+        self.views.iter().find(|v| v.id() == id)
+    }
+
+    pub fn get_view_mut(&mut self, id: &str) -> Option<&mut ViewStyle> {
+        self.views.iter_mut().find(|v| v.id() == id)
+    }
 }
 
 pub enum ShadowType {
+    /// Filtered by the PCF kernel of `ShadowStyle::pcf_kernel_size`.
     Normal,
     PCSS,
+    /// Exponential variance shadow map.
+    /// The moments of the depth are blurred by the PCF kernel, so the edge is soft without acne.
+    /// The point light uses `Normal` instead.
+    /// At least one light must use this type before the renderer is created to allocate the moments.
+    /// The moments take 8 bytes per texel for every layer of the shadow map in addition to the depth.
+    Variance,
 }
 
 impl ShadowType {
@@ -57,6 +130,7 @@ impl ShadowType {
         match self {
             ShadowType::Normal => 0,
             ShadowType::PCSS => 1,
+            ShadowType::Variance => 2,
         }
     }
 }
@@ -77,6 +151,30 @@ pub struct ShadowStyle {
     pub opacity: f32,
     #[getset(get = "pub", get_mut = "pub")]
     pub shadow_type: ShadowType,
+    /// Replaces the perspective projection with the cascaded orthographic projections.
+    /// This is used only for the directional light.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub cascade: Option<CascadeStyle>,
+    /// The constant depth bias added when the shadow map is baked.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub depth_bias: i32,
+    /// The depth bias scaled by the slope of the polygon.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub slope_scale_bias: f32,
+    /// The distance in world units to move the position along the normal before looking up the shadow map.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub normal_offset: f32,
+    /// The number of the texels on each side of the PCF kernel. `1` samples once.
+    /// This is not used for the point light.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub pcf_kernel_size: u32,
+    /// The resolution of the shadow map of this light. `None` uses `ShadowOptions::map_size`.
+    /// The point lights share the cube map of the largest resolution of them.
+    /// The shadow maps are allocated when the renderer is created, so the changes after that are ignored.
+    /// Every layer of the lights other than the point lights is allocated at the largest resolution
+    /// of them with 4 bytes per texel, and the resolution is capped by the device limit.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub map_size: Option<(u32, u32)>,
 }
 
 impl Default for ShadowStyle {
@@ -89,6 +187,12 @@ impl Default for ShadowStyle {
             up: CameraUp::default(),
             opacity: 1.,
             shadow_type: ShadowType::Normal,
+            cascade: None,
+            depth_bias: 2, // corresponds to bilinear filtering
+            slope_scale_bias: 2.0,
+            normal_offset: 0.,
+            pcf_kernel_size: 1,
+            map_size: None,
         }
     }
 }
@@ -99,22 +203,164 @@ impl ShadowStyle {
     pub(super) fn transform(&self, light: &LightStyle) -> Mat4 {
         let projection =
             glam::Mat4::perspective_rh(self.fov * consts::PI / 180., 1., self.near, self.far);
+        projection * self.view(light)
+    }
+
+    fn view(&self, light: &LightStyle) -> Mat4 {
         let view = glam::Mat4::look_at_rh(
             light.base().translation().as_glam(),
             self.center.0.as_glam(),
             self.up.0.as_glam(),
         );
-        let view = view
-            .mul_mat4(&Mat4::from_rotation_x(light.base().rotation_x()))
+        view.mul_mat4(&Mat4::from_rotation_x(light.base().rotation_x()))
             .mul_mat4(&Mat4::from_rotation_y(light.base().rotation_y()))
-            .mul_mat4(&Mat4::from_rotation_z(light.base().rotation_z()));
-        projection * view
+            .mul_mat4(&Mat4::from_rotation_z(light.base().rotation_z()))
+    }
+
+    // The number of the layers which the light uses in the shadow map.
+    // The point light uses the cube map instead.
+    pub(super) fn layer_len(&self, light: &LightStyle) -> u32 {
+        match (light.model(), &self.cascade) {
+            (LightModel::Directional, Some(cascade)) => {
+                cascade.count.clamp(1, CascadeStyle::MAX_COUNT)
+            }
+            (LightModel::Point, _) => 0,
+            _ => 1,
+        }
+    }
+
+    // Whether the projections are fitted to the camera, so they are recomputed when the camera is changed.
+    pub(super) fn follows_camera(&self, light: &LightStyle) -> bool {
+        matches!(
+            (light.model(), &self.cascade),
+            (LightModel::Directional, Some(_))
+        )
+    }
+
+    // Returns the projection of each layer of the shadow map.
+    // The cascades are fitted to the camera, and at most `max_count` cascades are returned.
+    pub(super) fn transforms(
+        &self,
+        light: &LightStyle,
+        camera: &CameraStyle,
+        max_count: u32,
+        map_size: u32,
+    ) -> Vec<Mat4> {
+        let cascade = match (light.model(), &self.cascade) {
+            (LightModel::Directional, Some(cascade)) => cascade,
+            (LightModel::Point, _) => return vec![],
+            _ => return vec![self.transform(light)],
+        };
+        let count = cascade.count.clamp(1, max_count.max(1));
+        let far = match cascade.max_distance > 0. {
+            true => cascade.max_distance.min(camera.far),
+            false => camera.far,
+        };
+        let depth = camera.far - camera.near;
+
+        // The corners of the near plane and the far plane.
+        // The view depth is linear along the line between them for both projections.
+        let inv = camera.transform().inverse();
+        let corners = [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].map(|(x, y)| {
+            (
+                inv.project_point3(glam::Vec3::new(x, y, 0.)),
+                inv.project_point3(glam::Vec3::new(x, y, 1.)),
+            )
+        });
+        let view = self.view(light);
+
+        cascade
+            .splits(camera.near, far, count)
+            .windows(2)
+            .map(|split| {
+                let (t0, t1) = (
+                    (split[0] - camera.near) / depth,
+                    (split[1] - camera.near) / depth,
+                );
+                let points = corners
+                    .iter()
+                    .flat_map(|(near, far)| [near.lerp(*far, t0), near.lerp(*far, t1)])
+                    .collect::<Vec<_>>();
+                let center = points.iter().sum::<glam::Vec3>() / points.len() as f32;
+                // The bounding sphere keeps the size of the projection while the camera rotates.
+                let radius = points.iter().map(|p| p.distance(center)).fold(0., f32::max);
+                let radius = (radius * 16.).ceil() / 16.;
+
+                // Snap the center to the texel to avoid the shimmering edges while the camera moves.
+                let center = view.transform_point3(center);
+                let texel = radius * 2. / map_size.max(1) as f32;
+                let x = (center.x / texel).floor() * texel;
+                let y = (center.y / texel).floor() * texel;
+                // The casters between the light and the slice are also included.
+                let near = (-center.z - radius).min(self.near);
+                let projection = glam::Mat4::orthographic_rh(
+                    x - radius,
+                    x + radius,
+                    y - radius,
+                    y + radius,
+                    near,
+                    -center.z + radius,
+                );
+                projection * view
+            })
+            .collect()
+    }
+}
+
+/// Split the view frustum of the main camera into the cascades of the orthographic shadow maps.
+/// The nearer cascade covers the smaller range, so the shadow near the camera is sharper.
+/// Each cascade uses a layer of the shadow map, so `count` can't be increased after the renderer is created.
+/// The cascades are fitted only to the main camera. The views and the render targets share them,
+/// so their cameras are shadowed only in the range which the cascades cover.
+#[derive(Getters, MutGetters)]
+pub struct CascadeStyle {
+    /// The number of the cascades in `1..=4`.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub count: u32,
+    /// Blends the uniform split (`0.`) and the logarithmic split (`1.`).
+    #[getset(get = "pub", get_mut = "pub")]
+    pub split_lambda: f32,
+    /// The shadow is cast until this distance from the camera. `0.` uses the far plane of the camera.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub max_distance: f32,
+    /// The width of the border in UV where the cascade is blended into the next cascade.
+    #[getset(get = "pub", get_mut = "pub")]
+    pub blend: f32,
+}
+
+impl Default for CascadeStyle {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            split_lambda: 0.5,
+            max_distance: 0.,
+            blend: 0.1,
+        }
+    }
+}
+
+impl CascadeStyle {
+    pub const MAX_COUNT: u32 = 4;
+
+    // The distances from the camera which separate the cascades.
+    fn splits(&self, near: f32, far: f32, count: u32) -> Vec<f32> {
+        // The logarithmic split needs the positive near plane.
+        let log_near = near.max(f32::EPSILON);
+        (0..=count)
+            .map(|i| {
+                let ratio = i as f32 / count as f32;
+                let log = log_near * (far / log_near).powf(ratio);
+                let uniform = near + (far - near) * ratio;
+                log * self.split_lambda + uniform * (1. - self.split_lambda)
+            })
+            .collect()
     }
 }
 
 #[derive(Getters, MutGetters)]
 pub struct ShadowOptions {
-    /// Defines resolution of shadow texture
+    /// Defines resolution of shadow texture.
+    /// The faces of the cube map for the point light use the shorter side.
     #[getset(get = "pub", get_mut = "pub")]
     pub map_size: (u32, u32),
 }
@@ -126,3 +372,50 @@ impl Default for ShadowOptions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vec3;
+
+    #[test]
+    fn test_cascade_transforms() {
+        let cascade = CascadeStyle::default();
+        let splits = cascade.splits(1., 100., 4);
+        assert_eq!(splits.first(), Some(&1.));
+        assert!((splits[4] - 100.).abs() < 1e-3);
+        assert!(splits.windows(2).all(|s| s[0] < s[1]));
+
+        let camera = CameraStyle {
+            width: 800.,
+            height: 600.,
+            ..Default::default()
+        };
+        let light = LightStyle::with_directional(
+            "directional".to_owned(),
+            LightBaseStyle {
+                position: Vec3::new(0., 60., 10.),
+                ..Default::default()
+            },
+            None,
+        );
+        let shadow = ShadowStyle {
+            cascade: Some(cascade),
+            ..Default::default()
+        };
+        let transforms = shadow.transforms(&light, &camera, 4, 512);
+        assert_eq!(transforms.len(), 4);
+        // Limited by the allocated layers
+        assert_eq!(shadow.transforms(&light, &camera, 2, 512).len(), 2);
+
+        // The center of each slice of the view frustum is in the cascade.
+        let eye = camera.calc_position_vec3().as_glam();
+        let forward = (camera.center.0.as_glam() - eye).normalize();
+        for (split, transform) in splits.windows(2).zip(&transforms) {
+            let point = eye + forward * (split[0] + split[1]) / 2.;
+            let p = transform.project_point3(point);
+            assert!(p.x.abs() <= 1. && p.y.abs() <= 1., "{p:?}");
+            assert!((0.0..=1.0).contains(&p.z), "{p:?}");
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use getset::{Getters, MutGetters, Setters};
+
+/// The operator to map the HDR color into the displayable range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// The color is clamped.
+    None,
+    Reinhard,
+    #[default]
+    Aces,
+}
+
+impl ToneMapping {
+    pub(crate) fn as_u32(&self) -> u32 {
+        match self {
+            ToneMapping::None => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::Aces => 2,
+        }
+    }
+}
+
+/// A full-screen pass which is applied to the HDR image before tone mapping.
+#[derive(Debug, Clone)]
+pub enum PostEffect {
+    /// Fast approximate anti-aliasing
+    Fxaa,
+    /// WGSL source which defines `fs_main(vertex: VertexOutput) -> @location(0) vec4<f32>`.
+    /// `VertexOutput`, `t_input`, `s_input` and `post_process` are declared before the source.
+    Custom { label: String, source: String },
+}
+
+/// The scene is rendered into the HDR target, and the effects are applied in order.
+/// Finally the image is scaled by the exposure and tone mapped into the output.
+/// The effects can't be changed after the renderer is created.
+#[derive(Debug, Clone, Getters, MutGetters, Setters)]
+pub struct PostProcessStyle {
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    tone_mapping: ToneMapping,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    exposure: f32,
+    #[getset(get = "pub")]
+    effects: Vec<PostEffect>,
+}
+
+impl Default for PostProcessStyle {
+    fn default() -> Self {
+        Self {
+            tone_mapping: Default::default(),
+            exposure: 1.,
+            effects: vec![],
+        }
+    }
+}
+
+impl PostProcessStyle {
+    pub fn with_tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+        self.tone_mapping = tone_mapping;
+        self
+    }
+
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn with_effect(mut self, effect: PostEffect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+}
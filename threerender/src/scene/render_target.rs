@@ -0,0 +1,94 @@
+use std::rc::Rc;
+
+use getset::{Getters, MutGetters};
+use threerender_color::rgb::RGBA;
+use threerender_traits::{image::Image, mesh::TextureFormat, types::Buffer};
+
+use super::CameraStyle;
+
+/// An offscreen render target which renders the scene from its own camera into a texture.
+/// The texture is rendered before the main view in each frame,
+/// so it can be used for the material of the other entities through `RenderTargetStyle::texture`.
+/// The entities which use the texture are still drawn into the render target itself,
+/// but they sample the empty placeholder instead of the texture.
+/// The cascaded shadow is fitted to the main camera, so the render target may be out of its range.
+#[derive(Getters, MutGetters)]
+pub struct RenderTargetStyle {
+    #[getset(get = "pub")]
+    id: String,
+    #[getset(get = "pub")]
+    width: u32,
+    #[getset(get = "pub")]
+    height: u32,
+    #[getset(get = "pub")]
+    camera: CameraStyle,
+    #[getset(get = "pub", get_mut = "pub")]
+    background: RGBA,
+    texture: Rc<dyn Image>,
+    // Incremented whenever the camera may be changed.
+    pub(crate) generation: u64,
+}
+
+impl RenderTargetStyle {
+    pub fn new(id: String, width: u32, height: u32, mut camera: CameraStyle) -> Self {
+        camera.width = width as f32;
+        camera.height = height as f32;
+        Self {
+            id,
+            width,
+            height,
+            camera,
+            background: RGBA::new(255, 255, 255, 255),
+            texture: Rc::new(RenderTargetImage {
+                width,
+                height,
+                format: TextureFormat::Rgba8,
+                data: vec![],
+            }),
+            generation: 0,
+        }
+    }
+
+    pub fn camera_mut(&mut self) -> &mut CameraStyle {
+        self.generation += 1;
+        &mut self.camera
+    }
+
+    pub fn with_background(mut self, background: RGBA) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// The texture to set to `Material::textures`.
+    pub fn texture(&self) -> Rc<dyn Image> {
+        self.texture.clone()
+    }
+}
+
+// A placeholder of the rendered texture. The data is never uploaded.
+#[derive(Debug)]
+struct RenderTargetImage {
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    data: Buffer,
+}
+
+impl Image for RenderTargetImage {
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn format(&self) -> &TextureFormat {
+        &self.format
+    }
+    fn data(&self) -> &Buffer {
+        &self.data
+    }
+    // Only the base level is rendered.
+    fn mipmaps(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,136 @@
+use getset::{Getters, MutGetters, Setters};
+use threerender_color::rgb::RGBA;
+
+use super::CameraStyle;
+
+/// A rectangle in the normalized coordinates of the render target.
+/// `(0, 0)` is the top-left corner and `(1, 1)` is the bottom-right corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // Returns `(x, y, width, height)` in pixels. The rectangle is clamped into the render target.
+    // The edges are rounded before the size is calculated not to exceed the render target.
+    pub(crate) fn to_pixels(self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let (w, h) = (width as f32, height as f32);
+        let x = (self.x * w).clamp(0., w).round();
+        let y = (self.y * h).clamp(0., h).round();
+        let right = ((self.x + self.width) * w).clamp(0., w).round().max(x);
+        let bottom = ((self.y + self.height) * h).clamp(0., h).round().max(y);
+        (x as u32, y as u32, (right - x) as u32, (bottom - y) as u32)
+    }
+
+    // Whether the point in pixels is inside the viewport.
+    pub(crate) fn contains(self, width: u32, height: u32, px: f32, py: f32) -> bool {
+        let (x, y, w, h) = self.to_pixels(width, height);
+        (x as f32..(x + w) as f32).contains(&px) && (y as f32..(y + h) as f32).contains(&py)
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::new(0., 0., 1., 1.)
+    }
+}
+
+/// How the area of the viewport is cleared before the view is drawn.
+#[derive(Debug, Clone)]
+pub enum ClearPolicy {
+    /// Draw over the previous views.
+    None,
+    /// Keep the color of the previous views, but draw the entities in front of them.
+    Depth,
+    /// Fill the viewport with the color, and clear the depth.
+    Color(RGBA),
+}
+
+/// An additional camera which is drawn into the viewport of the same render target.
+/// The views are drawn after the main camera of `Scene` in ascending `order`,
+/// and share the shadow maps baked for the frame.
+/// The cascaded shadow is fitted to the main camera, so the view may be out of its range.
+#[derive(Getters, MutGetters, Setters)]
+pub struct ViewStyle {
+    #[getset(get = "pub")]
+    id: String,
+    #[getset(get = "pub")]
+    camera: CameraStyle,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    viewport: Viewport,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    clear: ClearPolicy,
+    #[getset(get = "pub", get_mut = "pub", set = "pub")]
+    order: i32,
+    // Incremented whenever the camera may be changed.
+    pub(crate) generation: u64,
+}
+
+impl ViewStyle {
+    pub fn new(id: String, camera: CameraStyle, viewport: Viewport) -> Self {
+        Self {
+            id,
+            camera,
+            viewport,
+            clear: ClearPolicy::Color(RGBA::new(255, 255, 255, 255)),
+            order: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn camera_mut(&mut self) -> &mut CameraStyle {
+        self.generation += 1;
+        &mut self.camera
+    }
+
+    pub fn with_clear(mut self, clear: ClearPolicy) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_to_pixels() {
+        assert_eq!(Viewport::default().to_pixels(800, 600), (0, 0, 800, 600));
+        assert_eq!(
+            Viewport::new(0.5, 0., 0.5, 1.).to_pixels(800, 600),
+            (400, 0, 400, 600)
+        );
+        // Clamped into the render target
+        assert_eq!(
+            Viewport::new(0.75, -0.5, 0.5, 1.).to_pixels(800, 600),
+            (600, 0, 200, 300)
+        );
+        // The odd size is not exceeded by rounding.
+        let (x, _, w, _) = Viewport::new(0.5, 0., 0.5, 1.).to_pixels(801, 600);
+        assert_eq!((x, w), (401, 400));
+        assert!(x + w <= 801);
+
+        let viewport = Viewport::new(0.5, 0., 0.5, 1.);
+        assert!(viewport.contains(800, 600, 400., 0.));
+        assert!(!viewport.contains(800, 600, 399.5, 0.));
+        assert!(!viewport.contains(800, 600, 800., 0.));
+        assert!(!Viewport::new(0., 0., 0., 0.).contains(800, 600, 0., 0.));
+    }
+}
@@ -1,13 +1,14 @@
 use std::{cell::RefCell, rc::Rc};
 
 use anyhow::Result;
-use gltf::mesh::util::{ReadIndices, ReadTexCoords};
+use gltf::mesh::util::ReadTexCoords;
 use threerender_color::rgb::RGBA;
 use threerender_math::Transform;
 use threerender_traits::{
-    entity::{EntityDescriptor, EntityRendererState, ReflectionStyle},
-    image::{DefaultImage, Image},
-    mesh::{vertex, Mesh, Vertex},
+    entity::{EntityDescriptor, EntityRendererState},
+    image::{AddressMode, DefaultImage, FilterMode, Image, SamplerDescriptor},
+    material::{Material as EntityMaterial, PBRStyle, ShadingModel, TextureSlot},
+    mesh::{vertex, CullMode, Mesh, TextureFormat, Vertex},
     types::Buffer,
 };
 
@@ -17,6 +18,7 @@ use super::{err::GltfError, fetcher::GltfFetcher, GltfHandler};
 pub struct GltfMesh {
     pub vertices: Rc<RefCell<Vec<Vertex>>>,
     pub indices: Option<Vec<u16>>,
+    pub indices_u32: Option<Vec<u32>>,
     pub tex_coords: Option<Vec<[f32; 2]>>,
     pub material: Option<Material>,
 }
@@ -26,11 +28,24 @@ impl GltfMesh {
         Self {
             vertices: Rc::new(RefCell::new(vec![])),
             indices: None,
+            indices_u32: None,
             tex_coords: None,
             material: None,
         }
     }
 
+    // Use 16-bit indices as long as possible to reduce the buffer size.
+    fn prepare_indices(&mut self, indices: Vec<u32>) {
+        if indices.is_empty() {
+            return;
+        }
+        if indices.iter().all(|i| *i <= u16::MAX as u32) {
+            self.indices = Some(indices.into_iter().map(|i| i as u16).collect());
+        } else {
+            self.indices_u32 = Some(indices);
+        }
+    }
+
     fn prepare_textures(&mut self) {
         let tex_coords = match &self.tex_coords {
             Some(c) => c,
@@ -55,6 +70,13 @@ impl Mesh for GltfMesh {
             None => None,
         }
     }
+
+    fn index_u32(&self) -> Option<&[u32]> {
+        match &self.indices_u32 {
+            Some(idx) => Some(idx),
+            None => None,
+        }
+    }
 }
 
 pub struct GltfLoader {
@@ -90,12 +112,20 @@ impl GltfLoader {
         let mut temp_meshes = vec![];
 
         let mut materials = vec![];
+        let mut entity_materials = vec![];
         for material in data.materials() {
-            materials.push(Material::from_material(&material, &mut fetcher)?);
+            let material = Material::from_material(&material, &mut fetcher)?;
+            entity_materials.push(Rc::new(material.to_entity_material()?));
+            materials.push(material);
         }
+        // The material of each mesh is shared between the entities.
+        let mut mesh_materials = vec![];
+        let mut mesh_states = vec![];
 
         for mesh in data.meshes() {
             let mut entity = GltfMesh::new();
+            let mut entity_material = None;
+            let mut indices_u32 = vec![];
 
             for prim in mesh.primitives() {
                 let reader = prim.reader(|b| buffers.get(b.index()).map(|v| &v[..]));
@@ -137,35 +167,7 @@ impl GltfLoader {
                 }
 
                 if let Some(indices) = reader.read_indices() {
-                    match indices {
-                        ReadIndices::U8(indices) => {
-                            let ei = &mut entity.indices;
-                            for idx in indices {
-                                match ei {
-                                    Some(ref mut ei) => ei.push(idx as u16),
-                                    None => *ei = Some(vec![idx as u16]),
-                                }
-                            }
-                        }
-                        ReadIndices::U16(indices) => {
-                            let ei = &mut entity.indices;
-                            for idx in indices {
-                                match ei {
-                                    Some(ref mut ei) => ei.push(idx),
-                                    None => *ei = Some(vec![idx]),
-                                }
-                            }
-                        }
-                        ReadIndices::U32(indices) => {
-                            let ei = &mut entity.indices;
-                            for idx in indices {
-                                match ei {
-                                    Some(ref mut ei) => ei.push(idx as u16),
-                                    None => *ei = Some(vec![idx as u16]),
-                                }
-                            }
-                        }
-                    }
+                    indices_u32.extend(indices.into_u32());
                 }
 
                 for tex in data.textures() {
@@ -210,11 +212,29 @@ impl GltfLoader {
                     .material()
                     .index()
                     .and_then(|i| materials.get(i).cloned());
+                entity_material = prim
+                    .material()
+                    .index()
+                    .and_then(|i| entity_materials.get(i).cloned());
 
                 entity.prepare_textures();
             }
 
+            entity.prepare_indices(indices_u32);
+
+            mesh_states.push(
+                entity
+                    .material
+                    .as_ref()
+                    .map_or_else(EntityRendererState::default, Material::to_entity_state),
+            );
             temp_meshes.push(Rc::new(entity));
+            mesh_materials.push(entity_material.unwrap_or_else(|| {
+                Rc::new(EntityMaterial {
+                    shading: ShadingModel::PBR,
+                    ..Default::default()
+                })
+            }));
         }
 
         // Flatting glTF children of node with mesh index.
@@ -242,32 +262,23 @@ impl GltfLoader {
                         .get(mesh_idx)
                         .expect("Mesh length hos to match with node index");
                     let mesh = mesh.clone();
-                    #[allow(unused_parens)]
-                    let (color, texture, normal_map) =
-                        mesh.material.as_ref().map_or_else(Default::default, |m| {
-                            (
-                                m.base_color,
-                                m.base_color_texture.as_ref().map(|b| b.image.clone()),
-                                m.normal_map.clone(),
-                            )
-                        });
-
-                    let normal_map = match normal_map {
-                        Some(n) => Some(Rc::new(DefaultImage::from_buffer(&n)?) as Rc<dyn Image>),
-                        None => None,
-                    };
+                    let material = mesh_materials
+                        .get(mesh_idx)
+                        .expect("Mesh length hos to match with node index")
+                        .clone();
+                    let state = mesh_states
+                        .get(mesh_idx)
+                        .expect("Mesh length hos to match with node index")
+                        .clone();
 
                     let mut desc = EntityDescriptor {
                         id: format!("{name}:{node_idx}"),
                         mesh: Some(mesh.clone()),
-                        fill_color: RGBA::from_f32(color[0], color[1], color[2], color[3]),
                         transform: node.local_transform,
-                        reflection: ReflectionStyle::default(),
+                        material,
                         children,
-                        state: EntityRendererState::default(),
-                        texture,
-                        normal_map,
-                        receive_shadow: true,
+                        state,
+                        instances: vec![],
                     };
                     handler.on_create(&mut desc, Some(&mesh), row_node);
                     Ok(desc)
@@ -276,14 +287,11 @@ impl GltfLoader {
                     let mut desc = EntityDescriptor {
                         id: format!("{name}:{node_idx}"),
                         mesh: None,
-                        fill_color: RGBA::default(),
                         transform: node.local_transform,
-                        reflection: ReflectionStyle::default(),
+                        material: Default::default(),
                         children,
                         state: EntityRendererState::default(),
-                        texture: None,
-                        normal_map: None,
-                        receive_shadow: true,
+                        instances: vec![],
                     };
                     handler.on_create(&mut desc, None, row_node);
                     Ok(desc)
@@ -297,14 +305,11 @@ impl GltfLoader {
             entities.push(EntityDescriptor {
                 id: format!("{name}:scene:{}", scene.index()),
                 mesh: None,
-                fill_color: RGBA::default(),
                 transform: Transform::default(),
-                reflection: ReflectionStyle::default(),
+                material: Default::default(),
                 children: search_node(scene.nodes().collect(), &f)?,
                 state: EntityRendererState::default(),
-                texture: None,
-                normal_map: None,
-                receive_shadow: true,
+                instances: vec![],
             });
             handler.after_root(&mut entities, &scene);
         }
@@ -340,9 +345,38 @@ impl GltfLoader {
     }
 }
 
+// The image is wrapped to carry the sampler of the glTF texture.
 #[derive(Debug, Clone)]
 pub struct MaterialTextureDescriptor {
     image: Rc<dyn Image>,
+    sampler: SamplerDescriptor,
+    // The color textures are sRGB encoded in glTF.
+    srgb: bool,
+    mipmaps: bool,
+}
+
+impl Image for MaterialTextureDescriptor {
+    fn width(&self) -> u32 {
+        self.image.width()
+    }
+    fn height(&self) -> u32 {
+        self.image.height()
+    }
+    fn format(&self) -> &TextureFormat {
+        match self.image.format() {
+            TextureFormat::Rgba8 if self.srgb => &TextureFormat::Rgba8Srgb,
+            format => format,
+        }
+    }
+    fn data(&self) -> &Buffer {
+        self.image.data()
+    }
+    fn sampler(&self) -> SamplerDescriptor {
+        self.sampler
+    }
+    fn mipmaps(&self) -> bool {
+        self.mipmaps && self.image.mipmaps()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -352,6 +386,9 @@ pub struct Material {
     pub metalness: f32,
     pub roughness: f32,
     pub normal_map: Option<Buffer>,
+    pub normal_map_sampler: SamplerDescriptor,
+    pub normal_map_mipmaps: bool,
+    pub double_sided: bool,
 }
 
 impl Material {
@@ -366,7 +403,13 @@ impl Material {
             Some(v) => {
                 let data = get_buffer_from_source(&v.texture().source().source(), fetcher)?;
                 let img = fetcher.load_image(data)?;
-                Some(MaterialTextureDescriptor { image: img })
+                let (sampler, mipmaps) = sampler_from_texture(&v.texture());
+                Some(MaterialTextureDescriptor {
+                    image: img,
+                    srgb: true,
+                    sampler,
+                    mipmaps,
+                })
             }
             None => None,
         };
@@ -374,12 +417,16 @@ impl Material {
         let metalness = pbr.metallic_factor();
         let roughness = pbr.roughness_factor();
 
-        let normal_map = match material.normal_texture() {
-            Some(n) => Some(get_buffer_from_source(
-                &n.texture().source().source(),
-                fetcher,
-            )?),
-            None => None,
+        let (normal_map, (normal_map_sampler, normal_map_mipmaps)) = match material.normal_texture()
+        {
+            Some(n) => (
+                Some(get_buffer_from_source(
+                    &n.texture().source().source(),
+                    fetcher,
+                )?),
+                sampler_from_texture(&n.texture()),
+            ),
+            None => (None, (Default::default(), true)),
         };
 
         Ok(Self {
@@ -388,8 +435,48 @@ impl Material {
             metalness,
             roughness,
             normal_map,
+            normal_map_sampler,
+            normal_map_mipmaps,
+            double_sided: material.double_sided(),
         })
     }
+
+    fn to_entity_state(&self) -> EntityRendererState {
+        EntityRendererState {
+            cull_mode: match self.double_sided {
+                true => CullMode::None,
+                false => CullMode::Back,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn to_entity_material(&self) -> Result<EntityMaterial, GltfError> {
+        let color = self.base_color;
+        let mut material = EntityMaterial {
+            shading: ShadingModel::PBR,
+            base_color: RGBA::from_f32(color[0], color[1], color[2], color[3]),
+            pbr: PBRStyle {
+                metalness: self.metalness,
+                roughness: self.roughness,
+            },
+            ..Default::default()
+        };
+        if let Some(texture) = &self.base_color_texture {
+            material.set_texture(TextureSlot::BaseColor, Rc::new(texture.clone()));
+        }
+        if let Some(normal_map) = &self.normal_map {
+            material.set_texture(
+                TextureSlot::Normal,
+                Rc::new(
+                    DefaultImage::from_buffer(normal_map)?
+                        .with_sampler(self.normal_map_sampler)
+                        .with_mipmaps(self.normal_map_mipmaps),
+                ) as Rc<dyn Image>,
+            );
+        }
+        Ok(material)
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -427,6 +514,56 @@ fn test_check_if_data_uri() {
     assert!(!check_if_data_uri("https://example.com"));
 }
 
+#[cfg(test)]
+#[test]
+fn test_prepare_indices() {
+    let mut mesh = GltfMesh::new();
+    mesh.prepare_indices(vec![0, 1, u16::MAX as u32]);
+    assert_eq!(mesh.index(), Some(&[0, 1, u16::MAX][..]));
+    assert_eq!(mesh.index_u32(), None);
+
+    let mut mesh = GltfMesh::new();
+    mesh.prepare_indices(vec![0, 1, u16::MAX as u32 + 1]);
+    assert_eq!(mesh.index(), None);
+    assert_eq!(mesh.index_u32(), Some(&[0, 1, u16::MAX as u32 + 1][..]));
+}
+
+// The default of glTF is repeat wrapping with the linear filters.
+// Returns the sampler and whether the mip chain is used.
+// `NEAREST` and `LINEAR` of the min filter don't use the mip chain.
+fn sampler_from_texture(texture: &gltf::Texture) -> (SamplerDescriptor, bool) {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let sampler = texture.sampler();
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => AddressMode::Repeat,
+    };
+    let (min_filter, mipmap_filter, mipmaps) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) => (FilterMode::Nearest, FilterMode::Nearest, false),
+        Some(MinFilter::Linear) => (FilterMode::Linear, FilterMode::Nearest, false),
+        Some(MinFilter::NearestMipmapNearest) => (FilterMode::Nearest, FilterMode::Nearest, true),
+        Some(MinFilter::NearestMipmapLinear) => (FilterMode::Nearest, FilterMode::Linear, true),
+        Some(MinFilter::LinearMipmapNearest) => (FilterMode::Linear, FilterMode::Nearest, true),
+        Some(MinFilter::LinearMipmapLinear) | None => {
+            (FilterMode::Linear, FilterMode::Linear, true)
+        }
+    };
+    let descriptor = SamplerDescriptor {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter: match sampler.mag_filter() {
+            Some(MagFilter::Nearest) => FilterMode::Nearest,
+            Some(MagFilter::Linear) | None => FilterMode::Linear,
+        },
+        min_filter,
+        mipmap_filter,
+        ..Default::default()
+    };
+    (descriptor, mipmaps)
+}
+
 fn get_buffer_from_source<F>(source: &gltf::image::Source, fetcher: &F) -> Result<Buffer, GltfError>
 where
     F: GltfFetcher,
@@ -0,0 +1,275 @@
+use std::{f32::consts, rc::Rc};
+
+use crate::{
+    image::{DefaultImage, Image},
+    mesh::TextureFormat,
+};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum CubemapError {
+    #[error("Image is empty: {width}x{height}")]
+    Empty { width: u32, height: u32 },
+    #[error("Face {face} has {actual} bytes, but at least {expected} bytes are expected")]
+    DataLength {
+        face: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Panorama has {actual} bytes, but at least {expected} bytes are expected")]
+    PanoramaDataLength { expected: usize, actual: usize },
+    #[error("Face {face} isn't square: {width}x{height}")]
+    NotSquare {
+        face: usize,
+        width: u32,
+        height: u32,
+    },
+    #[error("Face {face} has the size {actual}, but {expected} is expected")]
+    SizeMismatch {
+        face: usize,
+        expected: u32,
+        actual: u32,
+    },
+    #[error("Face {face} has the format {actual:?}, but {expected:?} is expected")]
+    FormatMismatch {
+        face: usize,
+        expected: TextureFormat,
+        actual: TextureFormat,
+    },
+}
+
+/// Six square images which surround the scene.
+/// The faces are ordered as `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`,
+/// and all faces must have the same size and format.
+#[derive(Debug, Clone)]
+pub struct Cubemap {
+    faces: [Rc<dyn Image>; 6],
+}
+
+impl Cubemap {
+    /// Returns an error if the faces are empty, aren't square, don't have the same size and format,
+    /// or have less data than their size.
+    pub fn new(faces: [Rc<dyn Image>; 6]) -> Result<Self, CubemapError> {
+        let (size, format) = (faces[0].width(), *faces[0].format());
+        for (face, image) in faces.iter().enumerate() {
+            let (width, height) = (image.width(), image.height());
+            if width == 0 || height == 0 {
+                return Err(CubemapError::Empty { width, height });
+            }
+            if width != height {
+                return Err(CubemapError::NotSquare {
+                    face,
+                    width,
+                    height,
+                });
+            }
+            if width != size {
+                return Err(CubemapError::SizeMismatch {
+                    face,
+                    expected: size,
+                    actual: width,
+                });
+            }
+            if *image.format() != format {
+                return Err(CubemapError::FormatMismatch {
+                    face,
+                    expected: format,
+                    actual: *image.format(),
+                });
+            }
+            let expected = data_length(image.as_ref());
+            if image.data().len() < expected {
+                return Err(CubemapError::DataLength {
+                    face,
+                    expected,
+                    actual: image.data().len(),
+                });
+            }
+        }
+        Ok(Self { faces })
+    }
+
+    /// Project the equirectangular panorama onto the faces of `size` pixels.
+    /// The center of the panorama faces `-Z`. The pixels are sampled with the nearest filter.
+    /// Returns an error if the panorama or `size` is empty, or the panorama has less data than its size.
+    pub fn from_equirectangular(image: &dyn Image, size: u32) -> Result<Self, CubemapError> {
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return Err(CubemapError::Empty { width, height });
+        }
+        let expected = data_length(image);
+        if image.data().len() < expected {
+            return Err(CubemapError::PanoramaDataLength {
+                expected,
+                actual: image.data().len(),
+            });
+        }
+        Self::new(
+            [0, 1, 2, 3, 4, 5]
+                .map(|face| Rc::new(project_equirectangular(image, face, size)) as Rc<dyn Image>),
+        )
+    }
+
+    pub fn faces(&self) -> &[Rc<dyn Image>; 6] {
+        &self.faces
+    }
+
+    pub fn size(&self) -> u32 {
+        self.faces[0].width()
+    }
+}
+
+// The length of the data which the image of its size must have.
+fn data_length(image: &dyn Image) -> usize {
+    image.width() as usize * image.height() as usize * image.bytes_per_pixel() as usize
+}
+
+// The direction of the texel in the face. `s` and `t` are in `-1..1` from the top-left corner.
+fn face_direction(face: usize, s: f32, t: f32) -> [f32; 3] {
+    match face {
+        0 => [1., -t, -s],
+        1 => [-1., -t, s],
+        2 => [s, 1., t],
+        3 => [s, -1., -t],
+        4 => [s, -t, 1.],
+        _ => [-s, -t, -1.],
+    }
+}
+
+fn project_equirectangular(image: &dyn Image, face: usize, size: u32) -> DefaultImage {
+    let (width, height) = (image.width(), image.height());
+    let bpp = image.bytes_per_pixel() as usize;
+    let src = image.data();
+
+    let mut data = Vec::with_capacity(size as usize * size as usize * bpp);
+    for y in 0..size {
+        for x in 0..size {
+            let s = (x as f32 + 0.5) / size as f32 * 2. - 1.;
+            let t = (y as f32 + 0.5) / size as f32 * 2. - 1.;
+            let [dx, dy, dz] = face_direction(face, s, t);
+            let len = (dx * dx + dy * dy + dz * dz).sqrt();
+
+            let u = 0.5 + dx.atan2(-dz) / consts::TAU;
+            let v = 0.5 - (dy / len).asin() / consts::PI;
+            let px = ((u * width as f32) as u32).min(width - 1);
+            let py = ((v * height as f32) as u32).min(height - 1);
+
+            let offset = (py * width + px) as usize * bpp;
+            data.extend_from_slice(&src[offset..offset + bpp]);
+        }
+    }
+
+    DefaultImage::new(size, size, *image.format(), data)
+        .with_sampler(image.sampler())
+        .with_mipmaps(image.mipmaps())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let face = |size, format| {
+            Rc::new(DefaultImage::new(
+                size,
+                size,
+                format,
+                vec![0; size as usize * size as usize * 4],
+            )) as Rc<dyn Image>
+        };
+        let faces = || [0; 6].map(|_| face(2, TextureFormat::Rgba8));
+        assert!(Cubemap::new(faces()).is_ok());
+
+        let mut mismatched = faces();
+        mismatched[3] = face(1, TextureFormat::Rgba8);
+        assert_eq!(
+            Cubemap::new(mismatched).unwrap_err(),
+            CubemapError::SizeMismatch {
+                face: 3,
+                expected: 2,
+                actual: 1
+            }
+        );
+
+        let mut mismatched = faces();
+        mismatched[5] = face(2, TextureFormat::Rgba8Srgb);
+        assert!(matches!(
+            Cubemap::new(mismatched),
+            Err(CubemapError::FormatMismatch { face: 5, .. })
+        ));
+
+        let mut mismatched = faces();
+        mismatched[0] = Rc::new(DefaultImage::new(2, 1, TextureFormat::Rgba8, vec![0; 8]));
+        assert!(matches!(
+            Cubemap::new(mismatched),
+            Err(CubemapError::NotSquare { face: 0, .. })
+        ));
+
+        let mut short = faces();
+        short[2] = Rc::new(DefaultImage::new(2, 2, TextureFormat::Rgba8, vec![0; 15]));
+        assert_eq!(
+            Cubemap::new(short).unwrap_err(),
+            CubemapError::DataLength {
+                face: 2,
+                expected: 16,
+                actual: 15
+            }
+        );
+
+        let empty = [0; 6].map(|_| face(0, TextureFormat::Rgba8));
+        assert_eq!(
+            Cubemap::new(empty).unwrap_err(),
+            CubemapError::Empty {
+                width: 0,
+                height: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_equirectangular_invalid() {
+        let empty = DefaultImage::new(0, 4, TextureFormat::Rgba8, vec![]);
+        assert_eq!(
+            Cubemap::from_equirectangular(&empty, 1).unwrap_err(),
+            CubemapError::Empty {
+                width: 0,
+                height: 4
+            }
+        );
+
+        let short = DefaultImage::new(2, 2, TextureFormat::Rgba8, vec![0; 8]);
+        assert_eq!(
+            Cubemap::from_equirectangular(&short, 1).unwrap_err(),
+            CubemapError::PanoramaDataLength {
+                expected: 16,
+                actual: 8
+            }
+        );
+
+        let panorama = DefaultImage::new(2, 1, TextureFormat::Rgba8, vec![0; 8]);
+        assert!(matches!(
+            Cubemap::from_equirectangular(&panorama, 0),
+            Err(CubemapError::Empty { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_equirectangular() {
+        // Each column has its index as the color.
+        let panorama = DefaultImage::new(
+            8,
+            1,
+            TextureFormat::Rgba8,
+            (0..8).flat_map(|i| [i, 0, 0, 255]).collect(),
+        );
+        let cubemap = Cubemap::from_equirectangular(&panorama, 1).unwrap();
+        assert_eq!(cubemap.size(), 1);
+
+        let column = |face: usize| cubemap.faces()[face].data()[0];
+        // +X is on the right of -Z.
+        assert_eq!(column(0), 6);
+        assert_eq!(column(1), 2);
+        assert_eq!(column(4), 7);
+        assert_eq!(column(5), 4);
+    }
+}
@@ -1,12 +1,11 @@
 use std::rc::Rc;
 
-use getset::{Getters, MutGetters, Setters};
 use threerender_color::rgb::RGBA;
 use threerender_math::Transform;
 
 use crate::{
-    image::Image,
-    mesh::{Mesh, PolygonMode, Topology},
+    material::Material,
+    mesh::{BlendMode, CullMode, FrontFace, Mesh, PolygonMode, Topology},
 };
 
 /// A descriptor to setup an entity to the renderer.
@@ -14,14 +13,13 @@ use crate::{
 pub struct EntityDescriptor {
     pub id: String,
     pub mesh: Option<Rc<dyn Mesh>>,
-    pub fill_color: RGBA,
     pub transform: Transform,
-    pub reflection: ReflectionStyle,
+    pub material: Rc<Material>,
     pub children: Vec<EntityDescriptor>,
     pub state: EntityRendererState,
-    pub texture: Option<Rc<dyn Image>>,
-    pub normal_map: Option<Rc<dyn Image>>,
-    pub receive_shadow: bool,
+    /// Draw the mesh for each instance with a single draw call.
+    /// The mesh is drawn once without instance if this is empty.
+    pub instances: Vec<EntityInstance>,
 }
 
 impl Default for EntityDescriptor {
@@ -29,19 +27,22 @@ impl Default for EntityDescriptor {
         EntityDescriptor {
             id: "".to_string(),
             mesh: None,
-            fill_color: RGBA::default(),
             transform: Default::default(),
-            reflection: ReflectionStyle::default(),
+            material: Default::default(),
             children: vec![],
             state: EntityRendererState::default(),
-            texture: None,
-            normal_map: None,
-            receive_shadow: true,
+            instances: vec![],
         }
     }
 }
 
 impl EntityDescriptor {
+    /// Get the mutable material.
+    /// If the material is shared with other descriptors, the material is cloned for this descriptor.
+    pub fn material_mut(&mut self) -> &mut Material {
+        Rc::make_mut(&mut self.material)
+    }
+
     pub fn flatten_mesh_length(&self) -> usize {
         let v = match self.mesh {
             Some(_) => 1,
@@ -50,6 +51,16 @@ impl EntityDescriptor {
         v + Self::recursive_mesh_length(&self.children)
     }
 
+    /// Collect the renderer states of the descriptors and their children.
+    pub fn collect_states(descriptors: &[EntityDescriptor], states: &mut Vec<EntityRendererState>) {
+        for descriptor in descriptors {
+            if !states.contains(&descriptor.state) {
+                states.push(descriptor.state.clone());
+            }
+            Self::collect_states(&descriptor.children, states);
+        }
+    }
+
     pub fn recursive_mesh_length(children: &Vec<EntityDescriptor>) -> usize {
         let mut cnt = 0;
         for child in children {
@@ -62,16 +73,39 @@ impl EntityDescriptor {
     }
 }
 
+/// Per-instance attributes of the instanced entity.
+/// The transform is applied after the entity transform, and the color is multiplied by the material color.
+#[derive(Debug, Clone)]
+pub struct EntityInstance {
+    pub transform: Transform,
+    pub color: RGBA,
+}
+
+impl Default for EntityInstance {
+    fn default() -> Self {
+        Self {
+            transform: Transform::default(),
+            color: RGBA::new(255, 255, 255, 255),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct RendererState {
     pub topology: Topology,
     pub polygon_mode: PolygonMode,
+    pub blend: BlendMode,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
 }
 
 #[derive(Hash, PartialEq, Debug, Clone, Default)]
 pub struct EntityRendererState {
     pub topology: Topology,
     pub polygon_mode: PolygonMode,
+    pub blend: BlendMode,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
 }
 
 impl EntityRendererState {
@@ -79,33 +113,19 @@ impl EntityRendererState {
         Self {
             topology: state.topology,
             polygon_mode: state.polygon_mode,
+            blend: state.blend,
+            cull_mode: state.cull_mode,
+            front_face: state.front_face,
         }
     }
 }
 
 impl Eq for EntityRendererState {}
 
-#[derive(Debug, Clone, Getters, MutGetters, Setters)]
-pub struct ReflectionStyle {
-    pub intensity: f32,
-    pub specular: f32,
-}
-
-impl Default for ReflectionStyle {
-    fn default() -> Self {
-        Self {
-            intensity: 0.,
-            specular: 0.,
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use std::{cell::RefCell, rc::Rc};
 
-    use threerender_color::rgb::RGBA;
-
     use crate::mesh::{DefaultMesh, Mesh, Vertex};
 
     use super::EntityDescriptor;
@@ -127,26 +147,20 @@ mod test {
         let mut descriptor = EntityDescriptor {
             id: "".to_string(),
             mesh: Some(Rc::new(DefaultMesh)),
-            fill_color: RGBA::default(),
             transform: Default::default(),
-            reflection: super::ReflectionStyle::default(),
+            material: Default::default(),
             children: vec![],
             state: super::EntityRendererState::default(),
-            texture: None,
-            normal_map: None,
-            receive_shadow: true,
+            instances: vec![],
         };
         let mut descriptor_no_mesh = EntityDescriptor {
             id: "".to_string(),
             mesh: None,
-            fill_color: RGBA::default(),
             transform: Default::default(),
-            reflection: super::ReflectionStyle::default(),
+            material: Default::default(),
             children: vec![],
             state: super::EntityRendererState::default(),
-            texture: None,
-            normal_map: None,
-            receive_shadow: true,
+            instances: vec![],
         };
         descriptor.children.push(descriptor.clone());
         descriptor_no_mesh.children.push(descriptor.clone());
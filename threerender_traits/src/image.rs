@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use image::{load_from_memory, DynamicImage, ImageResult};
+use image::{load_from_memory, ColorType, DynamicImage, ImageResult};
 
 use crate::{mesh::TextureFormat, types::Buffer};
 
@@ -16,47 +16,136 @@ pub trait Image: Debug {
     fn data(&self) -> &Buffer;
     /// Texture bytes per pixel
     fn bytes_per_pixel(&self) -> u32 {
-        4
+        self.format().bytes_per_pixel()
     }
+    /// How the texture is sampled
+    fn sampler(&self) -> SamplerDescriptor {
+        SamplerDescriptor::default()
+    }
+    /// Generate the mip chain when the texture is uploaded
+    fn mipmaps(&self) -> bool {
+        true
+    }
+}
+
+/// How the texture coordinates outside of `0..1` are handled.
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum AddressMode {
+    #[default]
+    ClampToEdge,
+    Repeat,
+    MirrorRepeat,
+}
+
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum FilterMode {
+    Nearest,
+    #[default]
+    Linear,
 }
 
+/// Sampler settings of the texture.
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct SamplerDescriptor {
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    /// The maximum anisotropy. `1` disables the anisotropic filtering.
+    /// This is ignored unless all filters are `FilterMode::Linear`.
+    pub anisotropy: u16,
+}
+
+impl Default for SamplerDescriptor {
+    fn default() -> Self {
+        Self {
+            address_mode_u: Default::default(),
+            address_mode_v: Default::default(),
+            mag_filter: Default::default(),
+            min_filter: Default::default(),
+            mipmap_filter: Default::default(),
+            anisotropy: 1,
+        }
+    }
+}
+
+/// Use `DefaultImage::new` to create the image out of this crate,
+/// since the fields may be added in the future.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct DefaultImage {
     pub width: u32,
     pub height: u32,
     pub format: TextureFormat,
     pub data: Buffer,
+    pub sampler: SamplerDescriptor,
+    pub mipmaps: bool,
 }
 
 impl DefaultImage {
+    /// The image is sampled with the default sampler and the mip chain.
+    pub fn new(width: u32, height: u32, format: TextureFormat, data: Buffer) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            data,
+            sampler: Default::default(),
+            mipmaps: true,
+        }
+    }
+
     pub fn from_buffer(buffer: &[u8]) -> ImageResult<Self> {
         Ok(Self::from_image(load_from_memory(buffer)?))
     }
 
-    // TODO: improve image format data
+    /// The data is kept in its precision, and the gray scale is expanded into RGBA.
+    /// 8 bit color is treated as linear. Use `DefaultImage::with_srgb` for sRGB encoded color.
     pub fn from_image(img: DynamicImage) -> Self {
         let width = img.width();
         let height = img.height();
-        let format = match img.color() {
-            image::ColorType::Rgba8 => TextureFormat::Rgba8,
-            image::ColorType::Rgba16 => TextureFormat::Rgba8,
-            image::ColorType::Rgb8 => TextureFormat::Rgba8,
-            image::ColorType::Rgb16 => TextureFormat::Rgba8,
-            _ => unimplemented!(),
+        let (format, data) = match img.color() {
+            ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => {
+                (TextureFormat::Rgba8, img.into_rgba8().into_vec())
+            }
+            ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => (
+                TextureFormat::Rgba16,
+                img.into_rgba16()
+                    .into_vec()
+                    .iter()
+                    .flat_map(|c| c.to_le_bytes())
+                    .collect(),
+            ),
+            ColorType::Rgb32F | ColorType::Rgba32F => (
+                TextureFormat::Rgba32Float,
+                img.into_rgba32f()
+                    .into_vec()
+                    .iter()
+                    .flat_map(|c| c.to_le_bytes())
+                    .collect(),
+            ),
+            _ => (TextureFormat::Rgba8, img.into_rgba8().into_vec()),
         };
-        let data = match img.color() {
-            image::ColorType::Rgba8 => img.into_rgba8().into_vec(),
-            image::ColorType::Rgba16 => img.into_rgba8().into_vec(),
-            image::ColorType::Rgb8 => img.into_rgba8().into_vec(),
-            image::ColorType::Rgb16 => img.into_rgba8().into_vec(),
-            _ => unimplemented!(),
-        };
-        Self {
-            width,
-            height,
-            format,
-            data,
+        Self::new(width, height, format, data)
+    }
+
+    /// Treat the 8 bit color as sRGB encoded color, e.g. base color textures.
+    pub fn with_srgb(mut self) -> Self {
+        if self.format == TextureFormat::Rgba8 {
+            self.format = TextureFormat::Rgba8Srgb;
         }
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerDescriptor) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
     }
 }
 
@@ -73,4 +162,29 @@ impl Image for DefaultImage {
     fn data(&self) -> &Buffer {
         &self.data
     }
+    fn sampler(&self) -> SamplerDescriptor {
+        self.sampler
+    }
+    fn mipmaps(&self) -> bool {
+        self.mipmaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayAlphaImage, GrayImage, LumaA};
+
+    use super::*;
+
+    #[test]
+    fn test_from_image_gray() {
+        let img = DefaultImage::from_image(GrayImage::from_pixel(1, 1, [100].into()).into());
+        assert_eq!(img.format(), &TextureFormat::Rgba8);
+        assert_eq!(img.data(), &vec![100, 100, 100, 255]);
+
+        let img =
+            DefaultImage::from_image(GrayAlphaImage::from_pixel(1, 1, LumaA([100, 50])).into());
+        assert_eq!(img.format(), &TextureFormat::Rgba8);
+        assert_eq!(img.data(), &vec![100, 100, 100, 50]);
+    }
 }
@@ -1,4 +1,6 @@
+pub mod cubemap;
 pub mod entity;
 pub mod image;
+pub mod material;
 pub mod mesh;
 pub mod types;
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, rc::Rc};
+
+use getset::{Getters, MutGetters, Setters};
+use threerender_color::rgb::RGBA;
+
+use crate::image::Image;
+
+/// A material describes the surface of an entity.
+/// The material can be shared between multiple entities by wrapping it with `Rc`.
+#[derive(Debug, Clone, Getters, MutGetters, Setters)]
+pub struct Material {
+    pub shading: ShadingModel,
+    pub base_color: RGBA,
+    pub reflection: ReflectionStyle,
+    pub pbr: PBRStyle,
+    pub receive_shadow: bool,
+    pub textures: HashMap<TextureSlot, Rc<dyn Image>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            shading: ShadingModel::default(),
+            base_color: RGBA::default(),
+            reflection: ReflectionStyle::default(),
+            pbr: PBRStyle::default(),
+            receive_shadow: true,
+            textures: HashMap::new(),
+        }
+    }
+}
+
+impl Material {
+    pub fn texture(&self, slot: TextureSlot) -> Option<&Rc<dyn Image>> {
+        self.textures.get(&slot)
+    }
+
+    pub fn set_texture(&mut self, slot: TextureSlot, image: Rc<dyn Image>) {
+        self.textures.insert(slot, image);
+    }
+
+    pub fn with_texture(mut self, slot: TextureSlot, image: Rc<dyn Image>) -> Self {
+        self.set_texture(slot, image);
+        self
+    }
+}
+
+/// Shading model to calculate the lighting of an entity.
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum ShadingModel {
+    /// The base color is rendered as is without lighting.
+    Unlit,
+    /// Diffuse only.
+    Lambert,
+    /// Lambert diffuse with the specular reflection specified by `ReflectionStyle`.
+    #[default]
+    Phong,
+    /// Physically based shading with the metallic-roughness model specified by `PBRStyle`.
+    PBR,
+}
+
+/// Texture slots of the material.
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TextureSlot {
+    /// Multiplied by the base color.
+    BaseColor,
+    /// Tangent space normal map.
+    Normal,
+}
+
+#[derive(Debug, Clone, Getters, MutGetters, Setters)]
+pub struct ReflectionStyle {
+    pub intensity: f32,
+    pub specular: f32,
+}
+
+impl Default for ReflectionStyle {
+    fn default() -> Self {
+        Self {
+            intensity: 0.,
+            specular: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Getters, MutGetters, Setters)]
+pub struct PBRStyle {
+    /// `0.` is dielectric and `1.` is metal.
+    pub metalness: f32,
+    /// `0.` is smooth and `1.` is rough.
+    pub roughness: f32,
+}
+
+impl Default for PBRStyle {
+    fn default() -> Self {
+        Self {
+            metalness: 0.,
+            roughness: 1.,
+        }
+    }
+}
@@ -12,6 +12,12 @@ pub trait Mesh: Debug {
     /// Define indices to draw an entity more efficiently.
     fn index(&self) -> Option<&[u16]>;
 
+    /// Define 32-bit indices for the mesh which has more than 65,535 vertices.
+    /// This takes precedence over `index` if both are defined.
+    fn index_u32(&self) -> Option<&[u32]> {
+        None
+    }
+
     /// Set topology type. Default is `TriangleList`.
     fn topology(&self) -> Topology {
         Default::default()
@@ -23,16 +29,17 @@ pub trait Mesh: Debug {
         let mut vertices = vertex.borrow_mut();
         let mut triangles_included = vec![0; vertices.len()];
 
-        let mut default_index = vec![];
-        let index = self.index().map_or_else(
-            || {
-                for (i, _) in vertices.iter().enumerate() {
-                    default_index.push(i as u16);
-                }
-                &default_index[..]
-            },
-            |v| v,
-        );
+        // Reset to recalculate the tangent space of the mutated vertices.
+        for v in vertices.iter_mut() {
+            v.tangent = [0.; 3];
+            v.bitangent = [0.; 3];
+        }
+
+        let index: Vec<u32> = match (self.index_u32(), self.index()) {
+            (Some(index), _) => index.to_vec(),
+            (None, Some(index)) => index.iter().map(|i| *i as u32).collect(),
+            (None, None) => (0..vertices.len() as u32).collect(),
+        };
 
         // Calculate tangents and bitangets.
         // Loop index by each triangle
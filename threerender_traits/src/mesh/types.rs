@@ -14,8 +14,63 @@ pub enum PolygonMode {
     Point,
 }
 
-#[derive(Debug, Clone)]
+/// How the color of the entity is blended with the color behind it.
+/// The entity which is not `Opaque` is drawn after the opaque entities without writing depth.
+#[derive(Default, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    /// Blend with the alpha of the color.
+    Alpha,
+    /// Add the color multiplied by the alpha.
+    Additive,
+    /// Blend with the color which is already multiplied by the alpha.
+    Premultiplied,
+}
+
+/// Which face of the polygon is not drawn.
+#[derive(Default, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CullMode {
+    /// Both faces are drawn. This is useful for double-sided entities like a plane.
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+/// The winding order of the vertices which is treated as the front face.
+#[derive(Default, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum FrontFace {
+    /// Counter-clockwise
+    #[default]
+    Ccw,
+    /// Clockwise
+    Cw,
+}
+
+/// The pixel format of the image data. The channels are stored in little endian.
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
 pub enum TextureFormat {
+    /// Single channel, e.g. height or roughness maps.
+    R8,
+    Rg8,
+    /// Linear color
     Rgba8,
+    /// sRGB encoded color. This is converted to linear when it is sampled.
+    Rgba8Srgb,
     Rgba16,
+    /// e.g. HDR images
+    Rgba32Float,
+}
+
+impl TextureFormat {
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgba8 | TextureFormat::Rgba8Srgb => 4,
+            TextureFormat::Rgba16 => 8,
+            TextureFormat::Rgba32Float => 16,
+        }
+    }
 }